@@ -1,19 +1,480 @@
+use crate::config;
+use crate::operations::ExpiryAction;
+use crate::transport::record::{Amount, ClientId, Timestamp, TransactionId};
 use clap::Parser;
 use std::path::PathBuf;
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum FraudRuleKind {
+    /// Deposit withdrawn in full by the next transaction
+    RapidWithdrawal,
+    /// Client disputing too many transactions
+    RepeatedDisputes,
+    /// Too many accounts created in a short time
+    AccountBurst,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum StatementFormat {
+    Csv,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line, with transaction context as fields
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
 /// Simple Banking System
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, arg_required_else_help = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub log: LogOptions,
+
+    /// Options of processing when no subcommand is given
+    #[command(flatten)]
+    pub batch: BatchOptions,
+
+    #[command(flatten)]
+    pub output: OutputOptions,
+
+    #[command(flatten)]
+    pub statements: StatementOptions,
+
+    /// Input file, processed when no subcommand is given
+    #[arg()]
+    pub transactions: Option<PathBuf>,
+
+    /// Report what would happen, the same as `validate`
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl Cli {
+    /// Subcommand to run, `process` when only the input file is given
+    pub fn into_command(self) -> Option<Command> {
+        match (self.command, self.transactions) {
+            (Some(command), _) => Some(command),
+            (None, Some(transactions)) => Some(Command::Process(ProcessArgs {
+                log: self.log,
+                batch: self.batch,
+                output: self.output,
+                statements: self.statements,
+                transactions,
+                dry_run: self.dry_run,
+            })),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Process transactions and print resulting accounts, default when no subcommand is given
+    Process(ProcessArgs),
+    /// Check records of the input file without printing accounts or writing any files
+    Validate(ValidateArgs),
+    /// Process several input files in order, optionally up to the given time
+    Replay(ReplayArgs),
+    /// Process transactions and print selected accounts or their statements
+    Query(QueryArgs),
+    /// Process transactions and compare resulting accounts with expected ones
+    Reconcile(ReconcileArgs),
+    /// Process transactions streamed to the standard input
+    Serve(ServeArgs),
+    /// Process transactions and print summary of the accounts
+    Stats(StatsArgs),
+}
+
+impl Command {
+    pub fn log(&self) -> &LogOptions {
+        match self {
+            Command::Process(args) => &args.log,
+            Command::Validate(args) => &args.log,
+            Command::Replay(args) => &args.log,
+            Command::Query(args) => &args.log,
+            Command::Reconcile(args) => &args.log,
+            Command::Serve(args) => &args.log,
+            Command::Stats(args) => &args.log,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ProcessArgs {
+    #[command(flatten)]
+    pub log: LogOptions,
+
+    #[command(flatten)]
+    pub batch: BatchOptions,
+
+    #[command(flatten)]
+    pub output: OutputOptions,
+
+    #[command(flatten)]
+    pub statements: StatementOptions,
+
+    /// Input file
+    #[arg()]
+    pub transactions: PathBuf,
+
+    /// Report what would happen, the same as `validate`
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ValidateArgs {
+    #[command(flatten)]
+    pub log: LogOptions,
+
+    #[command(flatten)]
+    pub rules: RuleOptions,
+
+    /// Input file
+    #[arg()]
+    pub transactions: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ReplayArgs {
+    #[command(flatten)]
+    pub log: LogOptions,
+
+    #[command(flatten)]
+    pub batch: BatchOptions,
+
+    #[command(flatten)]
+    pub output: OutputOptions,
+
+    #[command(flatten)]
+    pub statements: StatementOptions,
+
+    /// Input files, processed in the given order
+    #[arg(required = true)]
+    pub transactions: Vec<PathBuf>,
+
+    /// Stop at the first record with timestamp past the given one
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub until: Option<Timestamp>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct QueryArgs {
+    #[command(flatten)]
+    pub log: LogOptions,
+
+    #[command(flatten)]
+    pub batch: BatchOptions,
+
+    #[command(flatten)]
+    pub output: OutputOptions,
+
+    /// Input file
+    #[arg()]
+    pub transactions: PathBuf,
+
+    /// Client to print, all clients when not provided
+    #[arg(long, value_name = "CLIENT")]
+    pub client: Vec<ClientId>,
+
+    /// Print statements instead of accounts
+    #[arg(long)]
+    pub statement: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ReconcileArgs {
+    #[command(flatten)]
+    pub log: LogOptions,
+
+    #[command(flatten)]
+    pub batch: BatchOptions,
+
+    /// Input file
+    #[arg()]
+    pub transactions: PathBuf,
+
+    /// Expected accounts file
+    #[arg()]
+    pub expected: PathBuf,
+
+    /// Number of digits past the decimal point taken into account when comparing amounts
+    #[arg(long, value_name = "DIGITS", default_value_t = 4)]
+    pub scale: u32,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub log: LogOptions,
+
+    #[command(flatten)]
+    pub batch: BatchOptions,
+
+    #[command(flatten)]
+    pub output: OutputOptions,
+
+    #[command(flatten)]
+    pub statements: StatementOptions,
+
+    /// Expose metrics in Prometheus text format under /metrics on the given address
+    #[arg(long, value_name = "HOST:PORT")]
+    pub metrics_address: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub log: LogOptions,
+
+    #[command(flatten)]
+    pub batch: BatchOptions,
+
+    /// Input file
+    #[arg()]
+    pub transactions: PathBuf,
+}
+
+/// Options of log messages, accepted by all subcommands
+#[derive(clap::Args, Debug)]
+pub struct LogOptions {
     /// Provide log messages
     #[arg(short, long)]
     pub log: bool,
 
+    /// Most verbose level of log messages
+    #[arg(long, value_name = "LEVEL", default_value = "debug")]
+    pub log_level: log::LevelFilter,
+
+    /// Write log messages to the given file instead of stderr
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Format of log messages
+    #[arg(long, value_enum, default_value = "text")]
+    pub log_format: LogFormat,
+}
+
+/// Business rules applied to each record, accepted by all subcommands
+#[derive(clap::Args, Debug)]
+pub struct RuleOptions {
+    /// Deny deposits to accounts that were not opened explicitly
+    #[arg(long)]
+    pub require_open: bool,
+
+    /// Alternative name of a transaction type, for instance withdraw=withdrawal
+    #[arg(long, value_name = "ALIAS=TYPE", value_parser = parse_type_alias)]
+    pub type_alias: Vec<(String, String)>,
+
+    /// Match transaction types and their aliases regardless of case
+    #[arg(long)]
+    pub case_insensitive_types: bool,
+
+    /// Allow reversals of deposits to take more than available funds
+    #[arg(long)]
+    pub allow_reversal_overdraft: bool,
+
+    /// Allow manual adjustments of frozen accounts
+    #[arg(long)]
+    pub allow_frozen_adjustments: bool,
+
+    /// Detect redelivered records, persisting processed ones in the given file
+    #[arg(long, value_name = "FILE")]
+    pub idempotency_store: Option<PathBuf>,
+
+    /// Maximal number of processed records remembered for detecting redeliveries
+    #[arg(long, value_name = "N")]
+    pub idempotency_capacity: Option<usize>,
+
+    /// Number of days after a deposit during which it can be disputed
+    #[arg(long, value_name = "DAYS", value_parser = days_parser())]
+    pub dispute_window_days: Option<u64>,
+
+    /// Number of days after which a dispute is resolved automatically
+    #[arg(long, value_name = "DAYS", value_parser = days_parser())]
+    pub dispute_deadline_days: Option<u64>,
+
+    /// Maximal amount of a single withdrawal
+    #[arg(long, value_name = "AMOUNT")]
+    pub max_withdrawal: Option<Amount>,
+
+    /// Maximal total amount of withdrawals within the velocity window
+    #[arg(long, value_name = "AMOUNT")]
+    pub max_withdrawal_total: Option<Amount>,
+
+    /// Maximal number of withdrawals within the velocity window
+    #[arg(long, value_name = "N")]
+    pub max_withdrawal_count: Option<usize>,
+
+    /// Velocity window, applies only when input contains timestamps
+    #[arg(long, value_name = "HOURS", default_value_t = 24, value_parser = hours_parser())]
+    pub velocity_window_hours: u64,
+
+    /// CSV file with per-client withdrawal limits (client, max_amount, max_total, max_count)
+    #[arg(long, value_name = "FILE")]
+    pub client_limits: Option<PathBuf>,
+
+    /// Fraud detection rules to apply
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub fraud_rules: Vec<FraudRuleKind>,
+
+    /// Lock accounts on fraud alerts
+    #[arg(long)]
+    pub fraud_lock: bool,
+
+    /// Number of disputes per client allowed before raising an alert
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub fraud_max_disputes: usize,
+
+    /// Number of accounts created within the window allowed before raising an alert
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    pub fraud_max_new_accounts: usize,
+
+    /// Number of records within which created accounts are counted
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    pub fraud_new_accounts_window: u64,
+}
+
+/// Options of subcommands that apply the records, run operations at the end of the batch
+/// and write their reports
+#[derive(clap::Args, Debug)]
+pub struct BatchOptions {
+    #[command(flatten)]
+    pub rules: RuleOptions,
+
     /// Print database to stderr for debugging purposes
     #[arg(short, long)]
     pub printdb: bool,
 
-    /// Input file
-    #[arg()]
-    pub transactions: PathBuf,
+    /// Abort on the first rejected record, without writing any output
+    #[arg(long, conflicts_with = "max_errors")]
+    pub strict: bool,
+
+    /// Abort when more than N records are rejected, without writing any output
+    #[arg(long, value_name = "N")]
+    pub max_errors: Option<u64>,
+
+    /// Write applied manual adjustments to the given file
+    #[arg(long, value_name = "FILE")]
+    pub adjustments_report: Option<PathBuf>,
+
+    /// Write fraud alerts to the given file
+    #[arg(long, value_name = "FILE")]
+    pub alerts: Option<PathBuf>,
+
+    /// Number of days after which disputes still open at the end of the batch are expired
+    #[arg(long, value_name = "DAYS", value_parser = days_parser())]
+    pub expire_disputes_after_days: Option<u64>,
+
+    /// Number of transactions after which disputes still open at the end of the batch are expired
+    #[arg(long, value_name = "N")]
+    pub expire_disputes_after_transactions: Option<u64>,
+
+    /// How disputes are expired
+    #[arg(long, value_enum, default_value = "resolve")]
+    pub expire_disputes_action: ExpiryAction,
+
+    /// Write report of expired disputes to the given file
+    #[arg(long, value_name = "FILE")]
+    pub expiry_report: Option<PathBuf>,
+
+    /// Annual interest rate credited at the end of the batch, for instance 0.02
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    pub interest_rate: Option<Amount>,
+
+    /// Annual interest rate of accounts in the given tier, for instance gold=0.03
+    #[arg(long, value_name = "TIER=RATE", value_parser = parse_tier_rate)]
+    pub tier_interest_rate: Vec<(String, Amount)>,
+
+    /// Number of days for which interest is credited
+    #[arg(long, value_name = "DAYS", default_value_t = 30)]
+    pub interest_days: u64,
+
+    /// Highest transaction ID under which interest is recorded, lower IDs are taken when it is used
+    #[arg(long, value_name = "ID", default_value_t = TransactionId::MAX)]
+    pub interest_tx: TransactionId,
+
+    /// Write report of credited interest to the given file
+    #[arg(long, value_name = "FILE")]
+    pub interest_report: Option<PathBuf>,
+
+    /// Print processing statistics to stderr
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Format of the statistics
+    #[arg(long, value_enum, default_value = "text")]
+    pub stats_format: StatsFormat,
+}
+
+/// Options of subcommands that print accounts
+#[derive(clap::Args, Debug)]
+pub struct OutputOptions {
+    /// Add closed status of accounts to the output
+    #[arg(long)]
+    pub show_closed: bool,
+
+    /// Add credit limit and remaining headroom of accounts to the output
+    #[arg(long)]
+    pub show_limits: bool,
+}
+
+/// Options of subcommands that write statements of the accounts
+#[derive(clap::Args, Debug)]
+pub struct StatementOptions {
+    /// Write statements of the accounts to the given file
+    #[arg(long, value_name = "FILE")]
+    pub statements: Option<PathBuf>,
+
+    /// Limit statements to the given client
+    #[arg(long, value_name = "CLIENT")]
+    pub statement_client: Option<ClientId>,
+
+    /// Format of the statements
+    #[arg(long, value_enum, default_value = "csv")]
+    pub statement_format: StatementFormat,
+}
+
+fn hours_parser() -> clap::builder::RangedU64ValueParser {
+    clap::value_parser!(u64).range(..=config::MAX_HOURS)
+}
+
+fn days_parser() -> clap::builder::RangedU64ValueParser {
+    clap::value_parser!(u64).range(..=config::MAX_DAYS)
+}
+
+fn parse_rate(value: &str) -> Result<Amount, String> {
+    let rate: Amount = value.parse().map_err(|err| format!("{}", err))?;
+    if rate.is_sign_negative() {
+        return Err("rate must not be negative".to_string());
+    }
+    Ok(rate)
+}
+
+fn parse_tier_rate(value: &str) -> Result<(String, Amount), String> {
+    let Some((tier, rate)) = value.split_once('=') else {
+        return Err("expected TIER=RATE".to_string());
+    };
+    Ok((tier.to_string(), parse_rate(rate)?))
+}
+
+fn parse_type_alias(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((alias, name)) => Ok((alias.to_string(), name.to_string())),
+        None => Err("expected ALIAS=TYPE".to_string()),
+    }
 }
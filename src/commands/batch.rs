@@ -0,0 +1,288 @@
+use crate::cli::{
+    BatchOptions, FraudRuleKind, OutputOptions, RuleOptions, StatementFormat, StatementOptions,
+    StatsFormat,
+};
+use crate::config::{self, Config, VelocityLimits};
+use crate::database::{self, Account, IdempotencyStore, MemDatabase};
+use crate::dispatcher::Dispatcher;
+use crate::fraud::{AccountBurst, FraudRule, RapidWithdrawal, RepeatedDisputes};
+use crate::metrics::SharedMetrics;
+use crate::operations::{self, ExpiryPolicy, InterestPolicy};
+use crate::registry::TransactionRegistry;
+use crate::statistics::Statistics;
+use crate::transactions::TransactionError;
+use crate::transport::record::{ClientId, Record};
+use crate::transport::{self, CsvExporter, ImportError, JsonExporter};
+use std::error::Error;
+use std::fmt;
+
+/// Exit code reported when a check of the input or of the results fails
+pub const EXIT_CHECK_FAILED: u8 = 2;
+/// Exit code reported when processing is aborted by rejected records
+pub const EXIT_ABORTED: u8 = 3;
+
+/// Processing stopped by `--strict` or `--max-errors`, before any output was written
+#[derive(Debug)]
+pub struct BatchAborted {
+    pub rejected: u64,
+    /// Error of the record that exceeded the limit
+    pub last: TransactionError,
+}
+
+impl fmt::Display for BatchAborted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Processing aborted after {} rejected records",
+            self.rejected
+        )?;
+        if let Some(position) = self.last.position() {
+            write!(f, ", last at {}", position)?;
+        }
+        write!(f, ": {}", self.last.cause())
+    }
+}
+
+impl Error for BatchAborted {}
+
+/// Processes the records and runs operations that are due at the end of the batch.
+/// Reports requested by the options are written along the way, statements are only recorded
+/// for the caller when `record_statements` is given.
+pub fn run_batch(
+    options: &BatchOptions,
+    record_statements: bool,
+    rows: impl Iterator<Item = Result<Record, ImportError>>,
+    metrics: Option<SharedMetrics>,
+) -> Result<(MemDatabase, Statistics), Box<dyn Error>> {
+    let config = build_batch_config(options, record_statements)?;
+    let mut db = MemDatabase::new();
+    let mut dispatcher = build_dispatcher(&options.rules, &config, &mut db)?;
+    if let Some(metrics) = metrics {
+        dispatcher = dispatcher.with_metrics(metrics);
+    }
+
+    let max_errors = if options.strict {
+        Some(0)
+    } else {
+        options.max_errors
+    };
+    for row in rows {
+        dispatcher.dispatch(&row);
+        check_errors(&dispatcher, max_errors)?;
+    }
+    dispatcher.finish();
+    check_errors(&dispatcher, max_errors)?;
+
+    let idempotency_store = &options.rules.idempotency_store;
+    if let (Some(path), Some(store)) = (idempotency_store, dispatcher.idempotency()) {
+        store.save(path)?;
+    }
+
+    if let Some(path) = &options.alerts {
+        CsvExporter::new(csv::Writer::from_path(path)?).dump(dispatcher.alerts())?;
+    }
+    if let Some(path) = &options.adjustments_report {
+        let writer = csv::Writer::from_path(path)?;
+        CsvExporter::new(writer).dump(dispatcher.adjustments())?;
+    }
+    let mut statistics = dispatcher.into_statistics();
+
+    if let Some(policy) = &config.dispute_expiry {
+        let report = operations::expire_stale_disputes(
+            &mut db,
+            policy,
+            &mut statistics,
+            config.record_statements,
+        );
+        if let Some(path) = &options.expiry_report {
+            CsvExporter::new(csv::Writer::from_path(path)?).dump(&report)?;
+        }
+    }
+
+    if let Some(policy) = &config.interest {
+        let report =
+            operations::accrue_interest(&mut db, policy, &mut statistics, config.record_statements);
+        if let Some(path) = &options.interest_report {
+            CsvExporter::new(csv::Writer::from_path(path)?).dump(&report.rows())?;
+        }
+    }
+
+    if options.stats {
+        print_statistics(std::io::stderr(), options.stats_format, &statistics)?;
+    }
+
+    if options.printdb {
+        eprintln!("{:#?}", db);
+    }
+
+    Ok((db, statistics))
+}
+
+/// Fails once more than `max_errors` records are rejected, with the last rejection.
+pub fn check_errors(dispatcher: &Dispatcher, max_errors: Option<u64>) -> Result<(), BatchAborted> {
+    let rejected = dispatcher.statistics().rejected;
+    if max_errors.is_none_or(|max_errors| rejected <= max_errors) {
+        return Ok(());
+    }
+    match dispatcher.last_rejection() {
+        Some(last) => Err(BatchAborted {
+            rejected,
+            last: last.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Writes statements of the accounts when requested by the options.
+pub fn write_statements(
+    options: &StatementOptions,
+    db: &MemDatabase,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = &options.statements {
+        let statements = db.statements(options.statement_client);
+        match options.statement_format {
+            StatementFormat::Csv => {
+                CsvExporter::new(csv::Writer::from_path(path)?).dump(&statements)?
+            }
+            StatementFormat::Json => {
+                JsonExporter::new(std::fs::File::create(path)?).dump(&statements)?
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn build_dispatcher<'a>(
+    options: &RuleOptions,
+    config: &Config,
+    db: &'a mut MemDatabase,
+) -> Result<Dispatcher<'a>, Box<dyn Error>> {
+    let mut dispatcher = Dispatcher::new(db)
+        .with_config(config.clone())
+        .with_registry(build_registry(options)?);
+
+    if options.idempotency_store.is_some() || options.idempotency_capacity.is_some() {
+        let capacity = options
+            .idempotency_capacity
+            .unwrap_or(database::DEFAULT_CAPACITY);
+        let store = match &options.idempotency_store {
+            Some(path) if path.exists() => IdempotencyStore::load(path, capacity)?,
+            _ => IdempotencyStore::new(capacity),
+        };
+        dispatcher = dispatcher.with_idempotency(store);
+    }
+
+    for rule in build_rules(options) {
+        dispatcher = dispatcher.with_rule(rule);
+    }
+    Ok(dispatcher)
+}
+
+pub fn print_statistics<W: std::io::Write, T: serde::Serialize + std::fmt::Display>(
+    mut writer: W,
+    format: StatsFormat,
+    statistics: &T,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        StatsFormat::Text => write!(writer, "{}", statistics)?,
+        StatsFormat::Json => JsonExporter::new(writer).dump(statistics)?,
+    }
+    Ok(())
+}
+
+pub fn print_accounts<'a>(
+    options: &OutputOptions,
+    accounts: impl IntoIterator<Item = (&'a ClientId, &'a Account)>,
+) -> Result<(), csv::Error> {
+    let writer = csv::Writer::from_writer(std::io::stdout());
+    CsvExporter::new(writer)
+        .with_closed(options.show_closed)
+        .with_limits(options.show_limits)
+        .dump_accounts(accounts)
+}
+
+pub fn build_config(options: &RuleOptions) -> Result<Config, Box<dyn Error>> {
+    let mut config = Config {
+        dispute_window: options.dispute_window_days.map(config::days),
+        dispute_deadline: options.dispute_deadline_days.map(config::days),
+        require_open: options.require_open,
+        reversal_overdraft: options.allow_reversal_overdraft,
+        adjustments_on_frozen: options.allow_frozen_adjustments,
+        velocity_window: Some(config::hours(options.velocity_window_hours)),
+        withdrawal_limits: VelocityLimits {
+            max_amount: options.max_withdrawal,
+            max_total: options.max_withdrawal_total,
+            max_count: options.max_withdrawal_count,
+        },
+        ..Default::default()
+    };
+    if let Some(path) = &options.client_limits {
+        config.client_withdrawal_limits = transport::read_client_limits(path)?;
+    }
+    Ok(config)
+}
+
+/// Business rules together with operations that run at the end of the batch.
+fn build_batch_config(
+    options: &BatchOptions,
+    record_statements: bool,
+) -> Result<Config, Box<dyn Error>> {
+    let mut config = build_config(&options.rules)?;
+    config.record_statements = record_statements;
+    if options.expire_disputes_after_days.is_some()
+        || options.expire_disputes_after_transactions.is_some()
+    {
+        config.dispute_expiry = Some(ExpiryPolicy {
+            max_age: options.expire_disputes_after_days.map(config::days),
+            max_transactions: options.expire_disputes_after_transactions,
+            action: options.expire_disputes_action,
+        });
+    }
+    if options.interest_rate.is_some() || !options.tier_interest_rate.is_empty() {
+        config.interest = Some(InterestPolicy {
+            annual_rate: options.interest_rate.unwrap_or_default(),
+            tier_rates: options.tier_interest_rate.iter().cloned().collect(),
+            days: options.interest_days,
+            transaction_id: options.interest_tx,
+        });
+    }
+    Ok(config)
+}
+
+fn build_registry(options: &RuleOptions) -> Result<TransactionRegistry, Box<dyn Error>> {
+    let mut registry = TransactionRegistry::default();
+    for (alias, name) in &options.type_alias {
+        if registry.resolve(name) != Some(name) {
+            Err(format!(
+                "Alias {} of unknown transaction type: {}",
+                alias, name
+            ))?;
+        }
+        registry = registry.with_alias(alias, name);
+    }
+    if options.case_insensitive_types {
+        registry = registry.case_insensitive();
+    }
+    Ok(registry)
+}
+
+fn build_rules(options: &RuleOptions) -> Vec<Box<dyn FraudRule>> {
+    let lock = options.fraud_lock;
+    let rules = options
+        .fraud_rules
+        .iter()
+        .map(|kind| -> Box<dyn FraudRule> {
+            match kind {
+                FraudRuleKind::RapidWithdrawal => Box::new(RapidWithdrawal::new(lock)),
+                FraudRuleKind::RepeatedDisputes => {
+                    Box::new(RepeatedDisputes::new(options.fraud_max_disputes, lock))
+                }
+                FraudRuleKind::AccountBurst => Box::new(AccountBurst::new(
+                    options.fraud_max_new_accounts,
+                    options.fraud_new_accounts_window,
+                    lock,
+                )),
+            }
+        });
+    rules.collect()
+}
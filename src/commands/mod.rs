@@ -0,0 +1,17 @@
+pub mod batch;
+mod process;
+mod query;
+mod reconcile;
+mod replay;
+mod serve;
+mod stats;
+mod validate;
+
+pub use crate::commands::batch::{BatchAborted, EXIT_ABORTED};
+pub use crate::commands::process::process;
+pub use crate::commands::query::query;
+pub use crate::commands::reconcile::reconcile;
+pub use crate::commands::replay::replay;
+pub use crate::commands::serve::serve;
+pub use crate::commands::stats::stats;
+pub use crate::commands::validate::{validate, validate_input};
@@ -0,0 +1,19 @@
+use crate::cli::ProcessArgs;
+use crate::commands::batch::{print_accounts, run_batch, write_statements};
+use crate::commands::validate_input;
+use crate::transport::CsvImporter;
+use std::error::Error;
+use std::process::ExitCode;
+
+pub fn process(args: &ProcessArgs) -> Result<ExitCode, Box<dyn Error>> {
+    if args.dry_run {
+        return validate_input(&args.batch.rules, &args.transactions);
+    }
+    log::info!("Transactions file: {}", args.transactions.display());
+    let importer = CsvImporter::from_path(&args.transactions)?;
+    let record_statements = args.statements.statements.is_some();
+    let (db, _) = run_batch(&args.batch, record_statements, importer.into_rows(), None)?;
+    write_statements(&args.statements, &db)?;
+    print_accounts(&args.output, db.accounts())?;
+    Ok(ExitCode::SUCCESS)
+}
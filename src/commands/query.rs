@@ -0,0 +1,31 @@
+use crate::cli::QueryArgs;
+use crate::commands::batch::{print_accounts, run_batch};
+use crate::transport::{CsvExporter, CsvImporter};
+use std::error::Error;
+use std::process::ExitCode;
+
+pub fn query(args: &QueryArgs) -> Result<ExitCode, Box<dyn Error>> {
+    log::info!("Transactions file: {}", args.transactions.display());
+    let importer = CsvImporter::from_path(&args.transactions)?;
+    let (db, _) = run_batch(&args.batch, args.statement, importer.into_rows(), None)?;
+
+    let selected = |client_id| args.client.is_empty() || args.client.contains(client_id);
+    if args.statement {
+        let statements: Vec<_> = db
+            .statements(None)
+            .into_iter()
+            .filter(|entry| selected(&entry.client))
+            .collect();
+        let writer = csv::Writer::from_writer(std::io::stdout());
+        CsvExporter::new(writer).dump(&statements)?;
+    } else {
+        let mut accounts: Vec<_> = db
+            .accounts()
+            .iter()
+            .filter(|(client_id, _)| selected(client_id))
+            .collect();
+        accounts.sort_by_key(|(client_id, _)| **client_id);
+        print_accounts(&args.output, accounts)?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,27 @@
+use crate::cli::ReconcileArgs;
+use crate::commands::batch::{run_batch, EXIT_CHECK_FAILED};
+use crate::operations;
+use crate::transport::{self, CsvExporter, CsvImporter};
+use std::error::Error;
+use std::process::ExitCode;
+
+pub fn reconcile(args: &ReconcileArgs) -> Result<ExitCode, Box<dyn Error>> {
+    log::info!("Transactions file: {}", args.transactions.display());
+    let expected = transport::read_expected_accounts(&args.expected)?;
+    let importer = CsvImporter::from_path(&args.transactions)?;
+    let (db, _) = run_batch(&args.batch, false, importer.into_rows(), None)?;
+    let report = operations::reconcile(&db, &expected, args.scale);
+
+    let writer = csv::Writer::from_writer(std::io::stdout());
+    CsvExporter::new(writer).dump(&report)?;
+    if report.is_empty() {
+        log::info!("Accounts match the expected ones");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        log::warn!(
+            "Accounts differ from the expected ones in {} fields",
+            report.len()
+        );
+        Ok(ExitCode::from(EXIT_CHECK_FAILED))
+    }
+}
@@ -0,0 +1,28 @@
+use crate::cli::ReplayArgs;
+use crate::commands::batch::{print_accounts, run_batch, write_statements};
+use crate::transport::CsvImporter;
+use std::error::Error;
+use std::process::ExitCode;
+
+/// Processes input files one after another, as a single batch. With `until`, the state of
+/// accounts at the given time is reconstructed.
+pub fn replay(args: &ReplayArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let mut importers = Vec::new();
+    for path in args.transactions.iter() {
+        log::info!("Transactions file: {}", path.display());
+        importers.push(CsvImporter::from_path(path)?);
+    }
+
+    let rows = importers
+        .into_iter()
+        .flat_map(CsvImporter::into_rows)
+        .take_while(|row| match (row, args.until) {
+            (Ok(rec), Some(until)) => rec.timestamp.is_none_or(|timestamp| timestamp <= until),
+            _ => true,
+        });
+    let record_statements = args.statements.statements.is_some();
+    let (db, _) = run_batch(&args.batch, record_statements, rows, None)?;
+    write_statements(&args.statements, &db)?;
+    print_accounts(&args.output, db.accounts())?;
+    Ok(ExitCode::SUCCESS)
+}
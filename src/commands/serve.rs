@@ -0,0 +1,31 @@
+use crate::cli::ServeArgs;
+use crate::commands::batch::{print_accounts, run_batch, write_statements};
+use crate::metrics::SharedMetrics;
+use crate::transport::{self, CsvImporter};
+use std::error::Error;
+use std::process::ExitCode;
+
+/// Processes records as soon as they arrive on the standard input. Accounts are printed once
+/// the input is closed.
+pub fn serve(args: &ServeArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let metrics = match &args.metrics_address {
+        Some(address) => {
+            let metrics = SharedMetrics::default();
+            transport::expose_metrics(address, metrics.clone())?;
+            Some(metrics)
+        }
+        None => None,
+    };
+    log::info!("Reading transactions from the standard input");
+    let importer = CsvImporter::new(std::io::stdin());
+    let record_statements = args.statements.statements.is_some();
+    let (db, _) = run_batch(
+        &args.batch,
+        record_statements,
+        importer.into_rows(),
+        metrics,
+    )?;
+    write_statements(&args.statements, &db)?;
+    print_accounts(&args.output, db.accounts())?;
+    Ok(ExitCode::SUCCESS)
+}
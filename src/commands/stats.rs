@@ -0,0 +1,34 @@
+use crate::cli::StatsArgs;
+use crate::commands::batch::{print_statistics, run_batch};
+use crate::operations::{self, Summary};
+use crate::statistics::Statistics;
+use crate::transport::CsvImporter;
+use std::error::Error;
+use std::fmt;
+use std::process::ExitCode;
+
+#[derive(serde::Serialize)]
+struct StatsReport {
+    accounts: Summary,
+    processing: Statistics,
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.accounts, self.processing)
+    }
+}
+
+/// Prints summary of the accounts together with processing statistics.
+pub fn stats(args: &StatsArgs) -> Result<ExitCode, Box<dyn Error>> {
+    log::info!("Transactions file: {}", args.transactions.display());
+    let importer = CsvImporter::from_path(&args.transactions)?;
+    let (db, processing) = run_batch(&args.batch, false, importer.into_rows(), None)?;
+    let accounts = operations::summarize(&db);
+    let report = StatsReport {
+        accounts,
+        processing,
+    };
+    print_statistics(std::io::stdout(), args.batch.stats_format, &report)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,43 @@
+use crate::cli::{RuleOptions, ValidateArgs};
+use crate::commands::batch::{build_config, build_dispatcher, EXIT_CHECK_FAILED};
+use crate::database::MemDatabase;
+use crate::transport::{CsvExporter, CsvImporter};
+use std::error::Error;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Reports how many records would be completed, denied or rejected, grouped by reason.
+/// Records are processed on a scratch database, neither balances nor any files are written.
+pub fn validate(args: &ValidateArgs) -> Result<ExitCode, Box<dyn Error>> {
+    validate_input(&args.rules, &args.transactions)
+}
+
+pub fn validate_input(
+    options: &RuleOptions,
+    transactions: &Path,
+) -> Result<ExitCode, Box<dyn Error>> {
+    log::info!("Transactions file: {}", transactions.display());
+    let importer = CsvImporter::from_path(transactions)?;
+    let config = build_config(options)?;
+    let mut db = MemDatabase::new();
+    let mut dispatcher = build_dispatcher(options, &config, &mut db)?;
+
+    for row in importer.into_rows() {
+        dispatcher.dispatch(&row);
+    }
+    dispatcher.finish();
+
+    let statistics = dispatcher.statistics();
+    let outcomes: Vec<_> = statistics.outcomes().collect();
+    let writer = csv::Writer::from_writer(std::io::stdout());
+    CsvExporter::new(writer).dump(&outcomes)?;
+
+    let rejected = statistics.rejected;
+    if rejected == 0 {
+        log::info!("All {} records are valid", statistics.records);
+        Ok(ExitCode::SUCCESS)
+    } else {
+        log::warn!("Invalid records found: {}", rejected);
+        Ok(ExitCode::from(EXIT_CHECK_FAILED))
+    }
+}
@@ -0,0 +1,94 @@
+use crate::database::VelocityCounter;
+use crate::operations::{ExpiryPolicy, InterestPolicy};
+use crate::transactions::TransactionError;
+use crate::transport::record::{Amount, ClientId};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const SECONDS_PER_HOUR: u64 = 60 * 60;
+pub const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+/// Largest number of hours that fits into a `Duration` of seconds in `hours()`
+pub const MAX_HOURS: u64 = u64::MAX / SECONDS_PER_HOUR;
+/// Largest number of days that fits into a `Duration` of seconds in `days()`
+pub const MAX_DAYS: u64 = u64::MAX / SECONDS_PER_DAY;
+
+/// Business rules that are adjustable by the operator.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Time after a deposit during which it can be disputed
+    pub dispute_window: Option<Duration>,
+    /// Time after a dispute during which it must be resolved or charged back
+    pub dispute_deadline: Option<Duration>,
+    /// Handling of disputes that remain open at the end of the batch
+    pub dispute_expiry: Option<ExpiryPolicy>,
+    /// Interest credited at the end of the batch
+    pub interest: Option<InterestPolicy>,
+    /// Accounts are created only by explicit opening, not by deposits
+    pub require_open: bool,
+    /// Reversals of deposits may take more than available funds
+    pub reversal_overdraft: bool,
+    /// Manual adjustments are allowed on frozen accounts
+    pub adjustments_on_frozen: bool,
+    /// Applied transactions are appended to statements of the accounts
+    pub record_statements: bool,
+    /// Period of velocity limits, entire input when not provided
+    pub velocity_window: Option<Duration>,
+    /// Velocity limits of clients, unless overridden in `client_withdrawal_limits`
+    pub withdrawal_limits: VelocityLimits,
+    /// Limits of selected clients, a missing limit is taken from `withdrawal_limits`
+    pub client_withdrawal_limits: HashMap<ClientId, VelocityLimits>,
+}
+
+impl Config {
+    pub fn withdrawal_limits(&self, client_id: ClientId) -> VelocityLimits {
+        let global = &self.withdrawal_limits;
+        let Some(client) = self.client_withdrawal_limits.get(&client_id) else {
+            return global.clone();
+        };
+        VelocityLimits {
+            max_amount: client.max_amount.or(global.max_amount),
+            max_total: client.max_total.or(global.max_total),
+            max_count: client.max_count.or(global.max_count),
+        }
+    }
+}
+
+/// Anti-fraud caps of withdrawals, within the velocity window.
+#[derive(Debug, Clone, Default)]
+pub struct VelocityLimits {
+    pub max_amount: Option<Amount>,
+    pub max_total: Option<Amount>,
+    pub max_count: Option<usize>,
+}
+
+impl VelocityLimits {
+    pub fn check(&self, recent: &VelocityCounter, amount: Amount) -> Result<(), TransactionError> {
+        if self.max_amount.is_some_and(|max| amount > max) {
+            Err(TransactionError::exceed(
+                "Maximal amount of withdrawal exceeded",
+            ))?;
+        }
+        if self
+            .max_total
+            .is_some_and(|max| recent.total() + amount > max)
+        {
+            Err(TransactionError::exceed(
+                "Maximal total of withdrawals exceeded",
+            ))?;
+        }
+        if self.max_count.is_some_and(|max| recent.count() >= max) {
+            Err(TransactionError::exceed(
+                "Maximal number of withdrawals exceeded",
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn hours(hours: u64) -> Duration {
+    Duration::from_secs(hours * SECONDS_PER_HOUR)
+}
+
+pub fn days(days: u64) -> Duration {
+    Duration::from_secs(days * SECONDS_PER_DAY)
+}
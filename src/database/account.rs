@@ -1,14 +1,28 @@
-use crate::database::transfer::Transfer;
+use crate::database::moment::Moment;
+use crate::database::statement::StatementEntry;
+use crate::database::velocity::VelocityCounter;
+use crate::database::{Transfer, TransferState};
 use crate::transactions::TransactionError;
-use crate::transport::record::{Amount, TransactionId};
-use std::collections::HashMap;
+use crate::transport::record::{Amount, ClientId, Timestamp, TransactionId};
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Account {
+    /// Held by both disputes and authorizations
     pub amount_held: Amount,
     pub amount_total: Amount,
+    /// Amount by which available funds are allowed to go below zero
+    pub credit_limit: Amount,
     pub locked: bool,
+    pub closed: bool,
+    pub tier: Option<String>,
+    pub currency: Option<String>,
+    pub withdrawals: VelocityCounter,
     transfers: HashMap<TransactionId, Transfer>,
+    authorizations: HashMap<TransactionId, Amount>,
+    /// Authorizations that were captured or voided, so that their IDs are not reused
+    settled_authorizations: HashSet<TransactionId>,
+    statement: Vec<StatementEntry>,
 }
 
 impl Account {
@@ -16,14 +30,43 @@ impl Account {
         self.transfers.insert(transaction_id, transfer);
     }
 
-    pub fn remove_transfer(&mut self, transaction_id: &TransactionId) {
-        self.transfers.remove(transaction_id);
-    }
-
     pub fn contains_transfer(&self, transaction_id: &TransactionId) -> bool {
         self.transfers.contains_key(transaction_id)
     }
 
+    pub fn insert_authorization(&mut self, transaction_id: TransactionId, amount: Amount) {
+        self.authorizations.insert(transaction_id, amount);
+    }
+
+    /// Pending authorization, or one that was already captured or voided
+    pub fn contains_authorization(&self, transaction_id: &TransactionId) -> bool {
+        self.authorizations.contains_key(transaction_id)
+            || self.settled_authorizations.contains(transaction_id)
+    }
+
+    pub fn settle_authorization(&mut self, transaction_id: &TransactionId) {
+        self.authorizations.remove(transaction_id);
+        self.settled_authorizations.insert(*transaction_id);
+    }
+
+    pub fn try_get_authorization(
+        &self,
+        transaction_id: &TransactionId,
+    ) -> Result<Amount, TransactionError> {
+        if let Some(amount) = self.authorizations.get(transaction_id) {
+            return Ok(*amount);
+        }
+        Err(TransactionError::reject(
+            "Corresponding authorization not found",
+        ))
+    }
+
+    pub fn disputed_transfers(&self) -> impl Iterator<Item = (&TransactionId, &Transfer)> {
+        self.transfers
+            .iter()
+            .filter(|(_, transfer)| transfer.state == TransferState::Disputed)
+    }
+
     pub fn try_get_transfer_mut(
         &mut self,
         transaction_id: &TransactionId,
@@ -34,7 +77,66 @@ impl Account {
         Err(TransactionError::reject("Corresponding transfer not found"))
     }
 
+    /// Resolves disputes that were opened before `cutoff`, returning their transaction IDs.
+    pub fn resolve_overdue_disputes(&mut self, cutoff: Timestamp) -> Vec<TransactionId> {
+        let mut resolved = Vec::new();
+        for (transaction_id, transfer) in self.transfers.iter_mut() {
+            let disputed_at = transfer.disputed_at.and_then(|moment| moment.timestamp);
+            if disputed_at.is_none_or(|disputed_at| disputed_at >= cutoff) {
+                continue;
+            }
+            if let Ok(amount) = transfer.resolve(None) {
+                self.amount_held -= amount;
+                resolved.push(*transaction_id);
+            }
+        }
+        resolved
+    }
+
+    /// Time of the oldest dispute that is still open, when known
+    pub fn earliest_dispute(&self) -> Option<Timestamp> {
+        self.disputed_transfers()
+            .filter_map(|(_, transfer)| transfer.disputed_at?.timestamp)
+            .min()
+    }
+
+    /// Appends the transaction to the statement, with current balances of the account.
+    pub fn record_statement(
+        &mut self,
+        client_id: ClientId,
+        moment: Moment,
+        transaction_type: &str,
+        transaction_id: TransactionId,
+        total_before: Amount,
+    ) {
+        let entry = StatementEntry {
+            client: client_id,
+            sequence: moment.sequence,
+            timestamp: moment.timestamp,
+            r#type: transaction_type.to_string(),
+            tx: transaction_id,
+            amount: self.amount_total - total_before,
+            available: self.amount_available(),
+            held: self.amount_held,
+            total: self.amount_total,
+            status: self
+                .transfers
+                .get(&transaction_id)
+                .map(|transfer| transfer.state),
+        };
+        self.statement.push(entry);
+    }
+
+    pub fn statement(&self) -> &[StatementEntry] {
+        &self.statement
+    }
+
     pub fn amount_available(&self) -> Amount {
         self.amount_total - self.amount_held
     }
+
+    /// Funds that can be withdrawn, including unused overdraft
+    pub fn headroom(&self) -> Amount {
+        self.amount_available() + self.credit_limit
+    }
 }
@@ -0,0 +1,118 @@
+use crate::transactions::TransactionError;
+use crate::transport::record::{Amount, ClientId, TransactionId};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// Type, client, transaction and amount of the record, so that partial disputes of a transaction differ
+pub type IdempotencyKey = (String, ClientId, TransactionId, Option<Amount>);
+pub type Outcome = Result<(), TransactionError>;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StoredResult {
+    Completed,
+    Denied,
+    Rejected,
+    LimitExceeded,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct StoredOutcome {
+    r#type: String,
+    client: ClientId,
+    tx: TransactionId,
+    #[serde(default)]
+    amount: Option<Amount>,
+    outcome: StoredResult,
+    reason: String,
+    detail: Option<String>,
+}
+
+/// Outcomes of already processed records, used for detecting redelivered ones.
+/// When `capacity` is reached, the oldest outcomes are forgotten first.
+#[derive(Debug)]
+pub struct IdempotencyStore {
+    capacity: usize,
+    outcomes: HashMap<IdempotencyKey, Outcome>,
+    order: VecDeque<IdempotencyKey>,
+}
+
+impl IdempotencyStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            outcomes: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn load(path: &Path, capacity: usize) -> Result<Self, csv::Error> {
+        let mut store = Self::new(capacity);
+        let mut reader = csv::Reader::from_path(path)?;
+        for row in reader.deserialize::<StoredOutcome>() {
+            let stored = row?;
+            let error = match stored.outcome {
+                StoredResult::Completed => None,
+                StoredResult::Denied => Some(TransactionError::deny(stored.reason)),
+                StoredResult::Rejected => Some(TransactionError::reject(stored.reason)),
+                StoredResult::LimitExceeded => Some(TransactionError::exceed(stored.reason)),
+            };
+            let outcome = match (error, stored.detail) {
+                (None, _) => Ok(()),
+                (Some(err), Some(detail)) => Err(err.with_detail(detail)),
+                (Some(err), None) => Err(err),
+            };
+            let key = (stored.r#type, stored.client, stored.tx, stored.amount);
+            store.insert(key, outcome);
+        }
+        Ok(store)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+        for key in self.order.iter() {
+            let stored = &self.outcomes[key];
+            let outcome = match stored {
+                Ok(()) => StoredResult::Completed,
+                Err(TransactionError::Denied(_)) => StoredResult::Denied,
+                Err(TransactionError::Rejected(_)) => StoredResult::Rejected,
+                Err(TransactionError::LimitExceeded(_)) => StoredResult::LimitExceeded,
+            };
+            let error = stored.as_ref().err();
+            let reason = error.map_or("", TransactionError::reason).to_string();
+            let detail = error.and_then(TransactionError::detail).map(str::to_string);
+            let (r#type, client, tx, amount) = key.clone();
+            writer.serialize(StoredOutcome {
+                r#type,
+                client,
+                tx,
+                amount,
+                outcome,
+                reason,
+                detail,
+            })?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn get(&self, key: &IdempotencyKey) -> Option<&Outcome> {
+        self.outcomes.get(key)
+    }
+
+    pub fn insert(&mut self, key: IdempotencyKey, outcome: Outcome) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.outcomes.insert(key.clone(), outcome).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.outcomes.remove(&oldest);
+            }
+        }
+    }
+}
@@ -1,11 +1,16 @@
 use crate::database::account::Account;
+use crate::database::moment::Moment;
+use crate::database::statement::StatementEntry;
 use crate::transactions::TransactionError;
-use crate::transport::record::ClientId;
+use crate::transport::record::{ClientId, Timestamp};
 use std::collections::HashMap;
 
 #[derive(Default, Debug)]
 pub struct MemDatabase {
     accounts: HashMap<ClientId, Account>,
+    clock: Moment,
+    /// Prior state of accounts modified in the open transaction, `None` for created ones
+    journal: Option<HashMap<ClientId, Option<Account>>>,
 }
 
 impl MemDatabase {
@@ -13,17 +18,51 @@ impl MemDatabase {
         Self::default()
     }
 
+    pub fn now(&self) -> Moment {
+        self.clock
+    }
+
+    /// Moves the clock to the next record. Records that are out of order don't move it back.
+    pub fn advance_clock(&mut self, timestamp: Option<Timestamp>) {
+        self.clock.sequence += 1;
+        self.clock.timestamp = self.clock.timestamp.max(timestamp);
+    }
+
     pub fn accounts(&self) -> &HashMap<ClientId, Account> {
         &self.accounts
     }
 
+    /// Statement entries of the given client or of all clients, ordered by client.
+    pub fn statements(&self, client_id: Option<ClientId>) -> Vec<&StatementEntry> {
+        let mut client_ids: Vec<_> = self
+            .accounts
+            .keys()
+            .filter(|id| client_id.is_none_or(|client_id| **id == client_id))
+            .collect();
+        client_ids.sort();
+        client_ids
+            .into_iter()
+            .flat_map(|id| self.accounts[id].statement())
+            .collect()
+    }
+
+    pub fn accounts_mut(&mut self) -> impl Iterator<Item = (&ClientId, &mut Account)> {
+        let client_ids: Vec<_> = self.accounts.keys().copied().collect();
+        for client_id in client_ids {
+            self.remember(client_id);
+        }
+        self.accounts.iter_mut()
+    }
+
     pub fn get_account(&mut self, client_id: ClientId) -> Result<&mut Account, TransactionError> {
+        self.remember(client_id);
         self.accounts
             .get_mut(&client_id)
             .ok_or(TransactionError::reject("Account not found"))
     }
 
     pub fn get_account_or_create(&mut self, client_id: ClientId) -> &mut Account {
+        self.remember(client_id);
         let account_entry = self.accounts.entry(client_id);
         account_entry.or_insert_with(|| {
             let account = Account::default();
@@ -31,4 +70,36 @@ impl MemDatabase {
             account
         })
     }
+
+    /// Starts a transaction, changes of accounts made from now on can be rolled back.
+    pub fn begin(&mut self) {
+        self.journal = Some(HashMap::new());
+    }
+
+    /// Keeps changes made in the transaction.
+    pub fn commit(&mut self) {
+        self.journal = None;
+    }
+
+    /// Restores accounts to their state from the beginning of the transaction.
+    pub fn rollback(&mut self) {
+        let Some(journal) = self.journal.take() else {
+            return;
+        };
+        for (client_id, account) in journal {
+            match account {
+                Some(account) => self.accounts.insert(client_id, account),
+                None => self.accounts.remove(&client_id),
+            };
+        }
+    }
+
+    /// Saves state of the account before its first change in the transaction.
+    fn remember(&mut self, client_id: ClientId) {
+        if let Some(journal) = &mut self.journal {
+            journal
+                .entry(client_id)
+                .or_insert_with(|| self.accounts.get(&client_id).cloned());
+        }
+    }
 }
@@ -1,7 +1,16 @@
 mod account;
+mod idempotency;
 mod memdb;
+mod moment;
+mod statement;
 mod transfer;
+mod velocity;
 
 pub use crate::database::account::Account;
+pub use crate::database::idempotency::{
+    IdempotencyKey, IdempotencyStore, Outcome, DEFAULT_CAPACITY,
+};
 pub use crate::database::memdb::MemDatabase;
-pub use crate::database::transfer::Transfer;
+pub use crate::database::moment::Moment;
+pub use crate::database::transfer::{Transfer, TransferKind, TransferState};
+pub use crate::database::velocity::VelocityCounter;
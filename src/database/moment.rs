@@ -0,0 +1,8 @@
+use crate::transport::record::Timestamp;
+
+/// Point of processing, given by the latest timestamp seen and the number of records processed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Moment {
+    pub timestamp: Option<Timestamp>,
+    pub sequence: u64,
+}
@@ -0,0 +1,19 @@
+use crate::database::TransferState;
+use crate::transport::record::{Amount, ClientId, Timestamp, TransactionId};
+
+/// Line of the account statement. Balances are taken after the transaction was applied.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StatementEntry {
+    pub client: ClientId,
+    pub sequence: u64,
+    pub timestamp: Option<Timestamp>,
+    pub r#type: String,
+    pub tx: TransactionId,
+    /// Change of total funds
+    pub amount: Amount,
+    pub available: Amount,
+    pub held: Amount,
+    pub total: Amount,
+    /// State of the transfer recorded under `tx`, if any
+    pub status: Option<TransferState>,
+}
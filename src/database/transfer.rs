@@ -1,7 +1,198 @@
-use crate::transport::record::Amount;
+use crate::database::Moment;
+use crate::transactions::TransactionError;
+use crate::transport::record::{Amount, Timestamp};
 
-#[derive(Debug, derive_new::new)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferState {
+    Settled,
+    Disputed,
+    Resolved,
+    ChargedBack,
+    /// Undone by an operator, which is final
+    Reversed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferKind {
+    Deposit,
+    /// Funds withdrawn by the client, amount is negative
+    Withdrawal,
+    /// Remaining funds paid out on closure of the account, amount is negative
+    Payout,
+    /// Interest credited by the bank, cannot be disputed
+    Interest,
+    /// Correction of the balance by an operator, cannot be disputed
+    Adjustment,
+}
+
+#[derive(Debug, Clone, derive_new::new)]
 pub struct Transfer {
+    pub kind: TransferKind,
     pub amount: Amount,
-    pub disputed: bool,
+    pub timestamp: Option<Timestamp>,
+    #[new(default)]
+    pub amount_disputed: Amount,
+    #[new(default)]
+    pub amount_charged_back: Amount,
+    #[new(value = "TransferState::Settled")]
+    pub state: TransferState,
+    #[new(default)]
+    pub disputed_at: Option<Moment>,
+}
+
+impl Transfer {
+    pub fn amount_undisputed(&self) -> Amount {
+        self.amount - self.amount_disputed - self.amount_charged_back
+    }
+
+    /// Moves the transfer to `Disputed`, returning the amount that must be held.
+    pub fn dispute(
+        &mut self,
+        amount: Option<Amount>,
+        amount_available: Amount,
+        now: Moment,
+    ) -> Result<Amount, TransactionError> {
+        if self.kind != TransferKind::Deposit {
+            Err(TransactionError::reject("Only deposits can be disputed"))?;
+        }
+
+        match self.state {
+            TransferState::Settled | TransferState::Disputed | TransferState::Resolved => {}
+            state => Err(Self::deny_in_state(state))?,
+        }
+
+        let amount_undisputed = self.amount_undisputed();
+        if amount_undisputed.is_zero() {
+            let msg = "Corresponding transfer already disputed";
+            Err(TransactionError::deny(msg))?;
+        }
+
+        let amount = amount.unwrap_or(amount_undisputed);
+        if amount > amount_undisputed {
+            let msg = "Disputed amount exceeds undisputed part of the transfer";
+            Err(TransactionError::deny(msg))?;
+        }
+
+        if amount_available < amount {
+            let msg = "Available funds are not sufficient";
+            Err(TransactionError::deny(msg))?;
+        }
+
+        if self.state != TransferState::Disputed {
+            self.disputed_at = Some(now);
+        }
+        self.amount_disputed += amount;
+        self.state = TransferState::Disputed;
+        Ok(amount)
+    }
+
+    /// Releases the disputed amount, returning the amount that is no longer held.
+    /// Transfer becomes `Resolved` once no part of it is disputed.
+    pub fn resolve(&mut self, amount: Option<Amount>) -> Result<Amount, TransactionError> {
+        let msg = "Resolved amount exceeds disputed part of the transfer";
+        let amount = self.disputed_part(amount, msg)?;
+
+        self.amount_disputed -= amount;
+        if self.amount_disputed.is_zero() {
+            self.state = TransferState::Resolved;
+            self.disputed_at = None;
+        }
+        Ok(amount)
+    }
+
+    /// Charges back the disputed amount, returning the amount that is withdrawn.
+    /// Transfer becomes `ChargedBack` once no part of it is disputed, which is final.
+    pub fn charge_back(&mut self, amount: Option<Amount>) -> Result<Amount, TransactionError> {
+        let msg = "Charged back amount exceeds disputed part of the transfer";
+        let amount = self.disputed_part(amount, msg)?;
+
+        self.amount_disputed -= amount;
+        self.amount_charged_back += amount;
+        if self.amount_disputed.is_zero() {
+            self.state = TransferState::ChargedBack;
+            self.disputed_at = None;
+        }
+        Ok(amount)
+    }
+
+    /// Charges back transfer that is not disputed, which is possible only after the account
+    /// is closed. Transfer becomes `ChargedBack`.
+    pub fn charge_back_late(&mut self, amount: Option<Amount>) -> Result<Amount, TransactionError> {
+        match self.state {
+            TransferState::Settled | TransferState::Resolved => {}
+            state => Err(Self::deny_in_state(state))?,
+        }
+        if self.kind != TransferKind::Deposit {
+            Err(TransactionError::reject(
+                "Only deposits can be charged back",
+            ))?;
+        }
+
+        let amount_undisputed = self.amount_undisputed();
+        let amount = amount.unwrap_or(amount_undisputed);
+        if amount > amount_undisputed {
+            let msg = "Charged back amount exceeds amount of the transfer";
+            Err(TransactionError::deny(msg))?;
+        }
+
+        self.amount_charged_back += amount;
+        self.state = TransferState::ChargedBack;
+        Ok(amount)
+    }
+
+    /// Moves the transfer to `Reversed`, returning the amount that is taken back from the account.
+    /// Amount of a reversed withdrawal is negative, as funds are given back.
+    /// Sufficient funds are required unless `amount_available` is not provided.
+    pub fn reverse(
+        &mut self,
+        amount_available: Option<Amount>,
+    ) -> Result<Amount, TransactionError> {
+        match self.kind {
+            TransferKind::Deposit | TransferKind::Withdrawal => {}
+            _ => Err(TransactionError::reject(
+                "Only deposits and withdrawals can be reversed",
+            ))?,
+        }
+        match self.state {
+            TransferState::Settled | TransferState::Resolved => {}
+            state => Err(Self::deny_in_state(state))?,
+        }
+
+        let amount = self.amount_undisputed();
+        if amount_available.is_some_and(|available| available < amount) {
+            let msg = "Available funds are not sufficient";
+            Err(TransactionError::deny(msg))?;
+        }
+
+        self.state = TransferState::Reversed;
+        Ok(amount)
+    }
+
+    fn disputed_part(
+        &self,
+        amount: Option<Amount>,
+        exceeded_msg: &str,
+    ) -> Result<Amount, TransactionError> {
+        if self.state != TransferState::Disputed {
+            Err(Self::deny_in_state(self.state))?;
+        }
+
+        let amount = amount.unwrap_or(self.amount_disputed);
+        if amount > self.amount_disputed {
+            Err(TransactionError::deny(exceeded_msg))?;
+        }
+        Ok(amount)
+    }
+
+    fn deny_in_state(state: TransferState) -> TransactionError {
+        TransactionError::deny(match state {
+            TransferState::Settled | TransferState::Resolved => {
+                "Corresponding transfer not disputed"
+            }
+            TransferState::Disputed => "Corresponding transfer already disputed",
+            TransferState::ChargedBack => "Corresponding transfer already charged back",
+            TransferState::Reversed => "Corresponding transfer reversed",
+        })
+    }
 }
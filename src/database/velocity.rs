@@ -0,0 +1,32 @@
+use crate::transport::record::{Amount, Timestamp};
+use std::collections::VecDeque;
+
+/// Recent withdrawals of an account, in order of their timestamps.
+#[derive(Debug, Default, Clone)]
+pub struct VelocityCounter {
+    withdrawals: VecDeque<(Option<Timestamp>, Amount)>,
+}
+
+impl VelocityCounter {
+    pub fn record(&mut self, timestamp: Option<Timestamp>, amount: Amount) {
+        self.withdrawals.push_back((timestamp, amount));
+    }
+
+    /// Forgets withdrawals made before `since`, including the ones with unknown timestamps.
+    pub fn forget_before(&mut self, since: Timestamp) {
+        while let Some((timestamp, _)) = self.withdrawals.front() {
+            if timestamp.is_some_and(|timestamp| timestamp >= since) {
+                break;
+            }
+            self.withdrawals.pop_front();
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.withdrawals.len()
+    }
+
+    pub fn total(&self) -> Amount {
+        self.withdrawals.iter().map(|(_, amount)| amount).sum()
+    }
+}
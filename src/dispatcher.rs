@@ -1,64 +1,523 @@
-use crate::database::MemDatabase;
-use crate::transactions::{
-    Chargeback, Deposit, Dispute, Resolve, Transaction, TransactionError, Withdrawal,
+use crate::config::Config;
+use crate::database::{IdempotencyKey, IdempotencyStore, MemDatabase, Outcome};
+use crate::fraud::{Alert, Event, FraudRule};
+use crate::metrics::{self, SharedMetrics};
+use crate::registry::{Context, TransactionRegistry};
+use crate::statistics::{OutcomeKind, Statistics};
+use crate::transactions::{AdjustmentEntry, Transaction, TransactionError};
+use crate::transport::record::{
+    Amount, ClientId, Record, SourcePosition, Timestamp, TransactionId,
 };
-use crate::transport::record::{ClientId, Record};
+use crate::transport::ImportError;
+use std::time::Instant;
+
+const BATCH_BEGIN: &str = "batch_begin";
+const BATCH_COMMIT: &str = "batch_commit";
+
+/// Records enclosed by `batch_begin` and `batch_commit`, applied all or nothing
+struct Batch {
+    id: TransactionId,
+    position: Option<SourcePosition>,
+    /// Error of the first record that failed
+    failure: Option<TransactionError>,
+    /// Statistics from before the batch, restored on rollback
+    statistics: Statistics,
+    /// Outcomes remembered by the idempotency store once the batch is committed
+    outcomes: Vec<(IdempotencyKey, Outcome)>,
+    /// Number of adjustments reported before the batch
+    adjustments: usize,
+    /// Number of alerts raised before the batch
+    alerts: usize,
+    /// Fraud rules with their state from before the batch, restored on rollback
+    rules: Vec<Box<dyn FraudRule>>,
+    /// Opening record was counted in the statistics by `dispatch`
+    counted: bool,
+}
 
 #[derive(derive_new::new)]
 pub struct Dispatcher<'a> {
     db: &'a mut MemDatabase,
+    #[new(default)]
+    config: Config,
+    #[new(default)]
+    registry: TransactionRegistry,
+    #[new(default)]
+    idempotency: Option<IdempotencyStore>,
+    #[new(default)]
+    rules: Vec<Box<dyn FraudRule>>,
+    #[new(default)]
+    alerts: Vec<Alert>,
+    #[new(default)]
+    statistics: Statistics,
+    #[new(default)]
+    metrics: Option<SharedMetrics>,
+    #[new(default)]
+    last_rejection: Option<TransactionError>,
+    #[new(default)]
+    batch: Option<Batch>,
+    #[new(default)]
+    adjustments: Vec<AdjustmentEntry>,
+    /// Earliest time when some dispute is past its deadline, unknown until accounts are swept
+    #[new(default)]
+    next_deadline: Option<Timestamp>,
+    #[new(default)]
+    overdue_resolved: usize,
 }
 
 impl Dispatcher<'_> {
-    pub fn dispatch(&mut self, row: &Result<Record, csv::Error>) {
-        if let Err(err) = self.try_dispatch(row) {
-            match err {
-                TransactionError::Denied(cause) => log::warn!("Tranaction denied: {}", cause),
-                TransactionError::Rejected(cause) => log::error!("Tranaction rejected: {}", cause),
-            }
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_registry(mut self, registry: TransactionRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    pub fn with_idempotency(mut self, store: IdempotencyStore) -> Self {
+        self.idempotency = Some(store);
+        self
+    }
+
+    pub fn idempotency(&self) -> Option<&IdempotencyStore> {
+        self.idempotency.as_ref()
+    }
+
+    pub fn with_rule(mut self, rule: Box<dyn FraudRule>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn alerts(&self) -> &[Alert] {
+        &self.alerts
+    }
+
+    pub fn adjustments(&self) -> &[AdjustmentEntry] {
+        &self.adjustments
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Error of the most recently rejected record
+    pub fn last_rejection(&self) -> Option<&TransactionError> {
+        self.last_rejection.as_ref()
+    }
+
+    /// Rolls back the batch that is left open at the end of the input.
+    pub fn finish(&mut self) {
+        let Some(batch) = self.batch.take() else {
+            return;
+        };
+        let position = batch.position.clone();
+        let counted = batch.counted;
+        let failure = TransactionError::reject("Batch not committed").at(position.clone());
+        let err = self.rollback_batch(batch, failure);
+        log::error!("Tranaction rejected: {}", err.cause());
+        self.last_rejection = Some(err.clone());
+        if !counted {
+            return self.recount_metrics();
+        }
+        // The opening record reports the batch, as there is no commit
+        self.statistics.rolled_back -= 1;
+        self.statistics.records -= 1;
+        let outcome = Err(err);
+        let position = position.as_ref();
+        self.statistics
+            .count_outcome(Some(BATCH_BEGIN), position, &outcome);
+        self.recount_metrics();
+    }
+
+    pub fn into_statistics(self) -> Statistics {
+        self.statistics
+    }
+
+    /// Processes the record, logging its outcome and counting it in the statistics.
+    pub fn dispatch(&mut self, row: &Result<Record, ImportError>) {
+        let normalized = self.normalize(row);
+        let row = normalized.as_ref().unwrap_or(row);
+        let started = Instant::now();
+        let client_id = row.as_ref().ok().map(|rec| rec.client);
+        let account_before = self.account_gauges(client_id);
+        let rolled_back_before = self.statistics.rolled_back;
+        let overdue_resolved_before = self.overdue_resolved;
+        let batch_open_before = self.batch.is_some();
+        let outcome = self.try_dispatch(row);
+        let transaction_type = row.as_ref().ok().map(|rec| rec.r#type.as_str());
+        let position = match &outcome {
+            Ok(()) => row.as_ref().ok().and_then(|rec| rec.position.as_ref()),
+            Err(err) => err.position(),
+        };
+        Self::log_outcome(row, position, &outcome);
+        self.statistics
+            .count_outcome(transaction_type, position, &outcome);
+        if let Err(err @ TransactionError::Rejected(_)) = &outcome {
+            self.last_rejection = Some(err.clone());
+        }
+        if let Some(batch) = self.batch.as_mut().filter(|_| !batch_open_before) {
+            batch.counted = true;
+        }
+
+        if let Some(metrics) = &self.metrics {
+            let account = self.account_gauges(client_id);
+            let mut metrics = metrics
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let latency = started.elapsed();
+            let label = transaction_type.map(|name| self.metrics_label(name));
+            metrics.observe_transaction(label, OutcomeKind::of(&outcome), latency);
+            metrics.set_accounts(self.db.accounts().len());
+            let (held_before, locked_before) = account_before.unwrap_or_default();
+            let (held, locked) = account.unwrap_or_default();
+            metrics.update_account(held - held_before, locked_before, locked);
+        }
+        if self.statistics.rolled_back != rolled_back_before
+            || self.overdue_resolved != overdue_resolved_before
+        {
+            self.recount_metrics();
+        }
+    }
+
+    /// Type of the record as labelled in metrics, which is the same for all unknown types
+    fn metrics_label<'n>(&self, name: &'n str) -> &'n str {
+        let marker = matches!(name, BATCH_BEGIN | BATCH_COMMIT);
+        if marker || self.registry.resolve(name).is_some() {
+            name
         } else {
-            log::info!("Transation successfully completed");
+            metrics::UNKNOWN
+        }
+    }
+
+    /// Computes account gauges from all accounts, after many of them were changed at once
+    fn recount_metrics(&self) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        let accounts = self.db.accounts().values();
+        let held = accounts.clone().map(|account| account.amount_held).sum();
+        let locked = accounts.filter(|account| account.locked).count();
+        let mut metrics = metrics
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        metrics.set_accounts(self.db.accounts().len());
+        metrics.set_account_totals(held, locked);
+    }
+
+    /// Logs the outcome with context of the record as structured fields.
+    fn log_outcome(
+        row: &Result<Record, ImportError>,
+        position: Option<&SourcePosition>,
+        outcome: &Result<(), TransactionError>,
+    ) {
+        let (level, message) = match outcome {
+            Err(TransactionError::Denied(_)) => (log::Level::Warn, "Tranaction denied"),
+            Err(TransactionError::Rejected(_)) => (log::Level::Error, "Tranaction rejected"),
+            Err(TransactionError::LimitExceeded(_)) => {
+                (log::Level::Warn, "Tranaction exceeds limits")
+            }
+            Ok(()) => (log::Level::Info, "Transation successfully completed"),
+        };
+        if !log::log_enabled!(level) {
+            return;
+        }
+        let rec = row.as_ref().ok();
+        let cause = outcome.as_ref().err().map(TransactionError::cause);
+        log::log!(
+            level,
+            client = rec.map(|rec| rec.client),
+            tx = rec.map(|rec| rec.tx),
+            "type" = rec.map(|rec| rec.r#type.as_str()),
+            file = position.and_then(|pos| pos.file.as_deref()),
+            line = position.map(|pos| pos.line),
+            byte = position.map(|pos| pos.byte),
+            outcome = OutcomeKind::of(outcome).key();
+            "{}{}",
+            message,
+            cause.map(|cause| format!(": {}", cause)).unwrap_or_default()
+        );
+    }
+
+    /// Held funds and lock of the account, when metrics are collected
+    fn account_gauges(&self, client_id: Option<ClientId>) -> Option<(Amount, bool)> {
+        self.metrics.as_ref()?;
+        let account = self.db.accounts().get(&client_id?)?;
+        Some((account.amount_held, account.locked))
+    }
+
+    /// Processes the record, attaching its position to the error.
+    /// Failure of a record in an open batch fails the whole batch.
+    pub fn try_dispatch(
+        &mut self,
+        row: &Result<Record, ImportError>,
+    ) -> Result<(), TransactionError> {
+        let normalized = self.normalize(row);
+        let row = normalized.as_ref().unwrap_or(row);
+        let outcome = match row {
+            Ok(rec) => self
+                .try_execute_once(rec)
+                .map_err(|err| err.at(rec.position.clone())),
+            Err(err) => {
+                let detail = err.error.to_string();
+                let error = TransactionError::reject("Malformed record").with_detail(detail);
+                Err(error.at(err.position.clone()))
+            }
+        };
+        if let (Some(batch), Err(err)) = (&mut self.batch, &outcome) {
+            batch.failure.get_or_insert_with(|| err.clone());
+        }
+        outcome
+    }
+
+    /// Copy of the record with canonical name of its type, when given by an alias or in other case.
+    /// Statistics, statements and fraud rules then see the same name regardless of the input.
+    fn normalize(&self, row: &Result<Record, ImportError>) -> Option<Result<Record, ImportError>> {
+        let rec = row.as_ref().ok()?;
+        let name = self.registry.resolve(&rec.r#type)?;
+        if name == rec.r#type {
+            return None;
+        }
+        let mut rec = rec.clone();
+        rec.r#type = name.to_string();
+        Some(Ok(rec))
+    }
+
+    /// Executes the record, unless it was already processed according to the idempotency store.
+    fn try_execute_once(&mut self, rec: &Record) -> Result<(), TransactionError> {
+        let marker = matches!(rec.r#type.as_str(), BATCH_BEGIN | BATCH_COMMIT);
+        let batch_failed = self.batch.as_ref().is_some_and(|b| b.failure.is_some());
+        if batch_failed && !marker {
+            Err(TransactionError::deny("Batch already failed"))?;
+        }
+        let Some(store) = self.idempotency.as_ref().filter(|_| !marker) else {
+            return self.try_execute(rec);
+        };
+        let key = (rec.r#type.clone(), rec.client, rec.tx, rec.given_amount());
+        if let Some(outcome) = store.get(&key) {
+            log::info!("Record already processed, replaying original outcome");
+            return outcome.clone();
+        }
+
+        let outcome = self.try_execute(rec);
+        if let Some(batch) = &mut self.batch {
+            batch.outcomes.push((key, outcome.clone()));
+        } else if let Some(store) = &mut self.idempotency {
+            store.insert(key, outcome.clone());
+        }
+        outcome
+    }
+
+    fn begin_batch(&mut self, rec: &Record) -> Result<(), TransactionError> {
+        if self.batch.is_some() {
+            Err(TransactionError::reject("Nested batches are not supported"))?;
         }
+        self.db.begin();
+        self.batch = Some(Batch {
+            id: rec.tx,
+            position: rec.position.clone(),
+            failure: None,
+            statistics: self.statistics.clone(),
+            outcomes: Vec::new(),
+            adjustments: self.adjustments.len(),
+            alerts: self.alerts.len(),
+            rules: self.rules.iter().map(|rule| rule.snapshot()).collect(),
+            counted: false,
+        });
+        Ok(())
     }
 
-    fn try_dispatch(&mut self, row: &Result<Record, csv::Error>) -> Result<(), TransactionError> {
-        let rec = {
-            match row {
-                Ok(record) => Ok(record),
-                Err(err) => Err(TransactionError::reject(format!("{}", err))),
+    fn commit_batch(&mut self, rec: &Record) -> Result<(), TransactionError> {
+        let Some(mut batch) = self.batch.take() else {
+            return Err(TransactionError::reject("No batch to commit"));
+        };
+        if batch.id != rec.tx && batch.failure.is_none() {
+            let failure = TransactionError::reject("Commit does not match the batch");
+            batch.failure = Some(failure.with_detail(batch.id.to_string()));
+        }
+        if let Some(failure) = batch.failure.take() {
+            return Err(self.rollback_batch(batch, failure));
+        }
+
+        self.db.commit();
+        if let Some(store) = &mut self.idempotency {
+            for (key, outcome) in batch.outcomes {
+                store.insert(key, outcome);
             }
-        }?;
+        }
+        log::debug!("== Batch committed: {}", batch.id);
+        Ok(())
+    }
+
+    /// Undoes changes of accounts made by the batch, counting its records as rolled back.
+    /// Returns error reporting the whole batch.
+    fn rollback_batch(&mut self, batch: Batch, failure: TransactionError) -> TransactionError {
+        self.db.rollback();
+        self.adjustments.truncate(batch.adjustments);
+        self.alerts.truncate(batch.alerts);
+        self.rules = batch.rules;
+        // Disputes resolved by the batch may be open again
+        self.next_deadline = None;
+        let records = self.statistics.records;
+        self.statistics = batch.statistics;
+        self.statistics.rolled_back += records - self.statistics.records;
+        self.statistics.records = records;
+        log::debug!("== Batch rolled back: {}", batch.id);
+        failure.context("Batch rolled back")
+    }
+
+    fn try_execute(&mut self, rec: &Record) -> Result<(), TransactionError> {
+        self.db.advance_clock(rec.timestamp);
+        let now = self.db.now();
+        self.resolve_overdue_disputes();
 
         match rec.r#type.as_str() {
-            "deposit" => self.process(rec.client, Deposit::new(rec.tx, rec.amount()?)),
-            "withdrawal" => self.process(rec.client, Withdrawal::new(rec.tx, rec.amount()?)),
-            "dispute" => self.process(rec.client, Dispute::new(rec.tx)),
-            "resolve" => self.process(rec.client, Resolve::new(rec.tx)),
-            "chargeback" => self.process(rec.client, Chargeback::new(rec.tx)),
+            BATCH_BEGIN => self.begin_batch(rec),
+            BATCH_COMMIT => self.commit_batch(rec),
             _ => {
-                let msg = format!("Invalid transaction type: {:?}", rec.r#type);
-                Err(TransactionError::reject(msg))
+                let context = Context {
+                    now,
+                    config: &self.config,
+                };
+                let transaction = self.registry.construct(rec, &context)?;
+                self.process(rec, transaction.as_ref())
+            }
+        }
+    }
+
+    /// Resolves disputes of all accounts that are past their deadline at the current time,
+    /// independently of the record being processed.
+    fn resolve_overdue_disputes(&mut self) {
+        let moment = self.db.now();
+        let (Some(now), Some(deadline)) = (moment.timestamp, self.config.dispute_deadline) else {
+            return;
+        };
+        let deadline = deadline.as_secs();
+        if self.next_deadline.is_none_or(|next| next <= now) {
+            let cutoff = now.saturating_sub(deadline);
+            let mut next = Timestamp::MAX;
+            for (client_id, account) in self.db.accounts_mut() {
+                for transaction_id in account.resolve_overdue_disputes(cutoff) {
+                    log::info!(
+                        "Dispute past deadline resolved, transaction: {}",
+                        transaction_id
+                    );
+                    if self.config.record_statements {
+                        let total = account.amount_total;
+                        account.record_statement(
+                            *client_id,
+                            moment,
+                            "resolve",
+                            transaction_id,
+                            total,
+                        );
+                    }
+                    self.overdue_resolved += 1;
+                }
+                if let Some(disputed_at) = account.earliest_dispute() {
+                    next = next.min(disputed_at.saturating_add(deadline + 1));
+                }
             }
+            self.next_deadline = Some(next);
         }
+        // The record may open a dispute that is past its deadline the earliest
+        let latest = now.saturating_add(deadline + 1);
+        self.next_deadline = self.next_deadline.map(|next| next.min(latest));
     }
 
     fn process(
         &mut self,
-        client_id: ClientId,
-        transaction: impl Transaction + std::fmt::Debug,
+        rec: &Record,
+        transaction: &dyn Transaction,
     ) -> Result<(), TransactionError> {
+        let client_id = rec.client;
         log::debug!("== Processing {:?} on account: {}", transaction, client_id);
+        let moment = self.db.now();
+        let now = moment.timestamp;
 
-        let account = if transaction.allowes_account_creation() {
+        let account_exists = self.db.accounts().contains_key(&client_id);
+        if transaction.opens_account() && account_exists {
+            Err(TransactionError::reject("Account already exists"))?;
+        }
+        let creation_allowed = transaction.opens_account()
+            || (transaction.allowes_account_creation() && !self.config.require_open);
+        let account_created = creation_allowed && !account_exists;
+        let account = if creation_allowed {
             self.db.get_account_or_create(client_id)
         } else {
             self.db.get_account(client_id)?
         };
+        if account_created {
+            self.statistics.accounts_created += 1;
+        }
+        let locked_before = account.locked;
 
-        if account.locked && !transaction.allowed_on_frozen_account() {
+        if account.closed {
+            if !transaction.allowed_on_closed_account() {
+                Err(TransactionError::deny("Not allowed on a closed account"))?;
+            }
+        } else if account.locked && !transaction.allowed_on_frozen_account() {
             Err(TransactionError::deny("Not allowed on a frozen account"))?;
         }
 
-        transaction.execute(account)
+        let withdrawn_amount = transaction.withdrawn_amount(account);
+        if let Some(amount) = withdrawn_amount {
+            if let (Some(now), Some(window)) = (now, self.config.velocity_window) {
+                let since = now.saturating_sub(window.as_secs());
+                account.withdrawals.forget_before(since);
+            }
+            let limits = self.config.withdrawal_limits(client_id);
+            limits.check(&account.withdrawals, amount)?;
+        }
+
+        let total_before = account.amount_total;
+        transaction.execute(account)?;
+        if self.config.record_statements {
+            account.record_statement(client_id, moment, &rec.r#type, rec.tx, total_before);
+        }
+        let change = account.amount_total - total_before;
+        self.statistics.count_funds(&rec.r#type, change);
+        if transaction.reported_as_adjustment() {
+            self.adjustments.push(AdjustmentEntry {
+                client: client_id,
+                tx: rec.tx,
+                timestamp: now,
+                amount: change,
+                reason: rec.reason.clone().unwrap_or_default(),
+                note: rec.note.clone(),
+            });
+        }
+
+        if let Some(amount) = withdrawn_amount {
+            account.withdrawals.record(now, amount);
+        }
+
+        for rule in self.rules.iter_mut() {
+            let event = Event {
+                record: rec,
+                account,
+                account_created,
+                now: moment,
+            };
+            let Some(detail) = rule.inspect(&event) else {
+                continue;
+            };
+            let lock = rule.locks_account();
+            if lock {
+                account.locked = true;
+            }
+            log::warn!("Fraud alert by {}: {}", rule.name(), detail);
+            let alert = Alert::new(rule.name(), client_id, rec.tx, detail, lock);
+            self.alerts.push(alert);
+        }
+        if account.locked && !locked_before {
+            self.statistics.accounts_locked += 1;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,48 @@
+use crate::fraud::{Event, FraudRule};
+use std::collections::VecDeque;
+
+/// Detects many accounts created within a short sequence of records.
+#[derive(Clone, derive_new::new)]
+pub struct AccountBurst {
+    max_accounts: usize,
+    window: u64,
+    lock: bool,
+    #[new(default)]
+    creations: VecDeque<u64>,
+}
+
+impl FraudRule for AccountBurst {
+    fn name(&self) -> &'static str {
+        "account_burst"
+    }
+
+    fn inspect(&mut self, event: &Event) -> Option<String> {
+        if !event.account_created {
+            return None;
+        }
+        let sequence = event.now.sequence;
+        while let Some(created) = self.creations.front() {
+            if sequence - created < self.window {
+                break;
+            }
+            self.creations.pop_front();
+        }
+        self.creations.push_back(sequence);
+        if self.creations.len() <= self.max_accounts {
+            return None;
+        }
+        Some(format!(
+            "{} accounts created within {} records",
+            self.creations.len(),
+            self.window
+        ))
+    }
+
+    fn locks_account(&self) -> bool {
+        self.lock
+    }
+
+    fn snapshot(&self) -> Box<dyn FraudRule> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,9 @@
+mod account_burst;
+mod rapid_withdrawal;
+mod repeated_disputes;
+mod rule;
+
+pub use crate::fraud::account_burst::AccountBurst;
+pub use crate::fraud::rapid_withdrawal::RapidWithdrawal;
+pub use crate::fraud::repeated_disputes::RepeatedDisputes;
+pub use crate::fraud::rule::{Alert, Event, FraudRule};
@@ -0,0 +1,44 @@
+use crate::fraud::{Event, FraudRule};
+use crate::transport::record::{Amount, ClientId};
+use std::collections::HashMap;
+
+/// Detects accounts emptied by a withdrawal that immediately follows a deposit.
+#[derive(Clone, derive_new::new)]
+pub struct RapidWithdrawal {
+    lock: bool,
+    #[new(default)]
+    last_deposits: HashMap<ClientId, Amount>,
+}
+
+impl FraudRule for RapidWithdrawal {
+    fn name(&self) -> &'static str {
+        "rapid_withdrawal"
+    }
+
+    fn inspect(&mut self, event: &Event) -> Option<String> {
+        let rec = event.record;
+        let deposited = self.last_deposits.remove(&rec.client);
+        match rec.r#type.as_str() {
+            "deposit" => {
+                let amount = rec.amount().ok()?;
+                self.last_deposits.insert(rec.client, amount);
+                None
+            }
+            "withdrawal" if event.account.amount_available() <= Amount::ZERO => {
+                let deposited = deposited?;
+                Some(format!(
+                    "Account emptied right after deposit of {deposited}"
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn locks_account(&self) -> bool {
+        self.lock
+    }
+
+    fn snapshot(&self) -> Box<dyn FraudRule> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,38 @@
+use crate::fraud::{Event, FraudRule};
+use crate::transport::record::ClientId;
+use std::collections::HashMap;
+
+/// Detects clients that dispute their transactions repeatedly.
+#[derive(Clone, derive_new::new)]
+pub struct RepeatedDisputes {
+    max_disputes: usize,
+    lock: bool,
+    #[new(default)]
+    disputes: HashMap<ClientId, usize>,
+}
+
+impl FraudRule for RepeatedDisputes {
+    fn name(&self) -> &'static str {
+        "repeated_disputes"
+    }
+
+    fn inspect(&mut self, event: &Event) -> Option<String> {
+        if event.record.r#type != "dispute" {
+            return None;
+        }
+        let disputes = self.disputes.entry(event.record.client).or_default();
+        *disputes += 1;
+        if *disputes <= self.max_disputes {
+            return None;
+        }
+        Some(format!("{} disputes by the same client", disputes))
+    }
+
+    fn locks_account(&self) -> bool {
+        self.lock
+    }
+
+    fn snapshot(&self) -> Box<dyn FraudRule> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,33 @@
+use crate::database::{Account, Moment};
+use crate::transport::record::{ClientId, Record, TransactionId};
+
+/// Successfully processed record, together with the resulting state of its account.
+pub struct Event<'a> {
+    pub record: &'a Record,
+    pub account: &'a Account,
+    pub account_created: bool,
+    pub now: Moment,
+}
+
+pub trait FraudRule {
+    fn name(&self) -> &'static str;
+
+    /// Returns description of suspicious activity, if detected.
+    fn inspect(&mut self, event: &Event) -> Option<String>;
+
+    fn locks_account(&self) -> bool {
+        false
+    }
+
+    /// Copy of the rule together with its state, restored when a batch is rolled back
+    fn snapshot(&self) -> Box<dyn FraudRule>;
+}
+
+#[derive(Debug, serde::Serialize, derive_new::new)]
+pub struct Alert {
+    pub rule: &'static str,
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub detail: String,
+    pub locked: bool,
+}
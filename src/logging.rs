@@ -1,14 +1,181 @@
-use simplelog::{ColorChoice, ConfigBuilder, LevelPadding, TermLogger, TerminalMode};
-
-pub fn setup() -> Result<(), log::SetLoggerError> {
-    TermLogger::init(
-        log::LevelFilter::Debug,
-        ConfigBuilder::new()
-            .set_level_padding(LevelPadding::Right)
-            .set_thread_level(log::LevelFilter::Off)
-            .set_target_level(log::LevelFilter::Off)
-            .build(),
-        TerminalMode::Stderr,
-        ColorChoice::Auto,
-    )
+use crate::cli::LogFormat;
+use log::kv::{self, Key, Value, VisitSource, VisitValue};
+use log::{LevelFilter, Log, Metadata, Record};
+use simplelog::{ColorChoice, ConfigBuilder, LevelPadding, TermLogger, TerminalMode, WriteLogger};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Installs the logger writing to stderr, or to the file when given.
+pub fn setup(
+    level: LevelFilter,
+    format: LogFormat,
+    file: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let logger: Box<dyn Log> = match (format, file) {
+        (LogFormat::Text, None) => Box::new(TextLogger(TermLogger::new(
+            level,
+            text_config(),
+            TerminalMode::Stderr,
+            ColorChoice::Auto,
+        ))),
+        (LogFormat::Text, Some(path)) => Box::new(TextLogger(WriteLogger::new(
+            level,
+            text_config(),
+            File::create(path)?,
+        ))),
+        (LogFormat::Json, None) => Box::new(JsonLogger::new(level, Box::new(std::io::stderr()))),
+        (LogFormat::Json, Some(path)) => {
+            Box::new(JsonLogger::new(level, Box::new(File::create(path)?)))
+        }
+    };
+    log::set_boxed_logger(logger)?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+fn text_config() -> simplelog::Config {
+    ConfigBuilder::new()
+        .set_level_padding(LevelPadding::Right)
+        .set_thread_level(LevelFilter::Off)
+        .set_target_level(LevelFilter::Off)
+        .build()
+}
+
+/// Appends context fields of the record to the message, as `key=value` pairs
+struct TextLogger(Box<dyn Log>);
+
+impl Log for TextLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let fields = fields(record);
+        if fields.is_empty() {
+            return self.0.log(record);
+        }
+        let context: String = fields
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| match value {
+                serde_json::Value::String(text) => format!(" {}={}", key, text),
+                other => format!(" {}={}", key, other),
+            })
+            .collect();
+        let args = format_args!("{}{}", record.args(), context);
+        self.0.log(&record.to_builder().args(args).build());
+    }
+
+    fn flush(&self) {
+        self.0.flush()
+    }
+}
+
+/// Writes every record as a single line JSON object
+struct JsonLogger {
+    level: LevelFilter,
+    output: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonLogger {
+    fn new(level: LevelFilter, output: Box<dyn Write + Send>) -> Self {
+        let output = Mutex::new(output);
+        Self { level, output }
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format_json(record) + "\n";
+        let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = output.write_all(line.as_bytes());
+    }
+
+    fn flush(&self) {
+        let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = output.flush();
+    }
+}
+
+/// Formats the record as a JSON object, with context fields at the top level.
+pub fn format_json(record: &Record) -> String {
+    let mut object = serde_json::Map::new();
+    let timestamp = OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_default();
+    object.insert("timestamp".into(), timestamp.into());
+    object.insert("level".into(), record.level().as_str().into());
+    object.insert("target".into(), record.target().into());
+    object.insert("message".into(), record.args().to_string().into());
+    object.extend(fields(record));
+    serde_json::Value::Object(object).to_string()
+}
+
+/// Context fields attached to the record, in the order they were given
+fn fields(record: &Record) -> Vec<(String, serde_json::Value)> {
+    let mut fields = Fields(Vec::new());
+    let _ = record.key_values().visit(&mut fields);
+    fields.0
+}
+
+struct Fields(Vec<(String, serde_json::Value)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let mut json = Json(serde_json::Value::Null);
+        value.visit(&mut json)?;
+        self.0.push((key.as_str().to_string(), json.0));
+        Ok(())
+    }
+}
+
+struct Json(serde_json::Value);
+
+impl VisitValue<'_> for Json {
+    fn visit_any(&mut self, value: Value) -> Result<(), kv::Error> {
+        self.0 = value.to_string().into();
+        Ok(())
+    }
+
+    fn visit_null(&mut self) -> Result<(), kv::Error> {
+        self.0 = serde_json::Value::Null;
+        Ok(())
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), kv::Error> {
+        self.0 = value.into();
+        Ok(())
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), kv::Error> {
+        self.0 = value.into();
+        Ok(())
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), kv::Error> {
+        self.0 = value.into();
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), kv::Error> {
+        self.0 = value.into();
+        Ok(())
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), kv::Error> {
+        self.0 = value.into();
+        Ok(())
+    }
 }
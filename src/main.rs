@@ -1,39 +1,53 @@
-use crate::database::MemDatabase;
-use crate::dispatcher::Dispatcher;
-use crate::transport::{CsvExporter, CvsFileImporter};
-use clap::Parser;
+use crate::cli::Command;
+use clap::{CommandFactory, Parser};
 use std::error::Error;
+use std::process::ExitCode;
 
 mod cli;
+mod commands;
+mod config;
 mod database;
 mod dispatcher;
+mod fraud;
 mod logging;
+mod metrics;
+mod operations;
+mod registry;
+mod statistics;
 mod tests;
 mod transactions;
 mod transport;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let cli_args = cli::Cli::parse();
-    if cli_args.log {
-        logging::setup()?;
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let command = match cli::Cli::parse().into_command() {
+        Some(command) => command,
+        None => {
+            let msg = "the following required argument was not provided: <TRANSACTIONS>";
+            cli::Cli::command()
+                .error(clap::error::ErrorKind::MissingRequiredArgument, msg)
+                .exit()
+        }
+    };
+    let options = command.log();
+    if options.log || options.log_file.is_some() {
+        let log_file = options.log_file.as_deref();
+        logging::setup(options.log_level, options.log_format, log_file)?;
     }
-    log::info!("Transactions file: {}", cli_args.transactions.display());
 
-    let mut importer = CvsFileImporter::new(cli_args.transactions)?;
-    let mut db = MemDatabase::new();
-    let mut dispatcher = Dispatcher::new(&mut db);
-
-    for row in importer.read_rows() {
-        dispatcher.dispatch(&row);
+    let result = match &command {
+        Command::Process(args) => commands::process(args),
+        Command::Validate(args) => commands::validate(args),
+        Command::Replay(args) => commands::replay(args),
+        Command::Query(args) => commands::query(args),
+        Command::Reconcile(args) => commands::reconcile(args),
+        Command::Serve(args) => commands::serve(args),
+        Command::Stats(args) => commands::stats(args),
+    };
+    match result {
+        Err(err) if err.is::<commands::BatchAborted>() => {
+            eprintln!("Error: {}", err);
+            Ok(ExitCode::from(commands::EXIT_ABORTED))
+        }
+        result => result,
     }
-
-    let writer = csv::Writer::from_writer(std::io::stdout());
-    let mut exporter = CsvExporter::new(writer);
-    exporter.dump_accounts(db.accounts())?;
-
-    if cli_args.printdb {
-        eprintln!("{:#?}", db);
-    }
-
-    Ok(())
 }
@@ -0,0 +1,149 @@
+use crate::statistics::OutcomeKind;
+use crate::transport::record::Amount;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Metrics are updated by `Dispatcher` and read by the metrics endpoint, from another thread
+pub type SharedMetrics = Arc<Mutex<Metrics>>;
+
+/// Upper bounds of latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 8] = [0.00001, 0.00005, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.1];
+
+/// Label of records that could not be parsed, hence have no type
+const MALFORMED: &str = "malformed";
+
+/// Label of records of types that are not registered, so that arbitrary input
+/// does not create new series
+pub const UNKNOWN: &str = "unknown";
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations per bucket of `LATENCY_BUCKETS`, not cumulative
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[index] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Counters, gauges and histograms exposed in Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    transactions: BTreeMap<(String, OutcomeKind), u64>,
+    latency: Histogram,
+    accounts: usize,
+    held: Amount,
+    locked: usize,
+}
+
+impl Metrics {
+    pub fn observe_transaction(
+        &mut self,
+        transaction_type: Option<&str>,
+        outcome: OutcomeKind,
+        latency: Duration,
+    ) {
+        let transaction_type = transaction_type.unwrap_or(MALFORMED).to_string();
+        *self
+            .transactions
+            .entry((transaction_type, outcome))
+            .or_default() += 1;
+        self.latency.observe(latency.as_secs_f64());
+    }
+
+    pub fn set_accounts(&mut self, accounts: usize) {
+        self.accounts = accounts;
+    }
+
+    /// Sets held funds and locked accounts computed from all accounts.
+    pub fn set_account_totals(&mut self, held: Amount, locked: usize) {
+        self.held = held;
+        self.locked = locked;
+    }
+
+    /// Applies change of held funds and of the lock of a single account.
+    pub fn update_account(&mut self, held_change: Amount, locked_before: bool, locked: bool) {
+        self.held += held_change;
+        match (locked_before, locked) {
+            (false, true) => self.locked += 1,
+            (true, false) => self.locked -= 1,
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            out,
+            "# HELP banking_transactions_total Processed records by type and outcome"
+        )?;
+        writeln!(out, "# TYPE banking_transactions_total counter")?;
+        for ((transaction_type, outcome), count) in self.transactions.iter() {
+            writeln!(
+                out,
+                "banking_transactions_total{{type=\"{}\",outcome=\"{}\"}} {}",
+                escape_label(transaction_type),
+                outcome.key(),
+                count
+            )?;
+        }
+
+        writeln!(
+            out,
+            "# HELP banking_processing_seconds Time of processing a single record"
+        )?;
+        writeln!(out, "# TYPE banking_processing_seconds histogram")?;
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(self.latency.buckets) {
+            cumulative += count;
+            writeln!(
+                out,
+                "banking_processing_seconds_bucket{{le=\"{}\"}} {}",
+                bound, cumulative
+            )?;
+        }
+        let count = self.latency.count;
+        writeln!(
+            out,
+            "banking_processing_seconds_bucket{{le=\"+Inf\"}} {}",
+            count
+        )?;
+        writeln!(out, "banking_processing_seconds_sum {}", self.latency.sum)?;
+        writeln!(out, "banking_processing_seconds_count {}", count)?;
+
+        writeln!(out, "# HELP banking_accounts Number of accounts")?;
+        writeln!(out, "# TYPE banking_accounts gauge")?;
+        writeln!(out, "banking_accounts {}", self.accounts)?;
+        writeln!(
+            out,
+            "# HELP banking_held_funds Sum of held funds of all accounts"
+        )?;
+        writeln!(out, "# TYPE banking_held_funds gauge")?;
+        writeln!(out, "banking_held_funds {}", self.held)?;
+        writeln!(
+            out,
+            "# HELP banking_locked_accounts Number of locked accounts"
+        )?;
+        writeln!(out, "# TYPE banking_locked_accounts gauge")?;
+        writeln!(out, "banking_locked_accounts {}", self.locked)
+    }
+}
+
+/// Types come from the input, so they must be escaped as label values.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,113 @@
+use crate::database::{MemDatabase, Moment};
+use crate::statistics::Statistics;
+use crate::transactions::{Chargeback, Resolve, Transaction};
+use crate::transport::record::{Amount, ClientId, TransactionId};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpiryAction {
+    Resolve,
+    Chargeback,
+}
+
+/// Disputes are stale once they exceed any of the limits.
+#[derive(Debug, Clone)]
+pub struct ExpiryPolicy {
+    pub max_age: Option<Duration>,
+    pub max_transactions: Option<u64>,
+    pub action: ExpiryAction,
+}
+
+impl ExpiryPolicy {
+    fn is_stale(&self, disputed_at: Moment, now: Moment) -> bool {
+        let too_old = match (self.max_age, disputed_at.timestamp, now.timestamp) {
+            (Some(max_age), Some(disputed_at), Some(now)) => {
+                now.saturating_sub(disputed_at) > max_age.as_secs()
+            }
+            _ => false,
+        };
+        let too_long = self
+            .max_transactions
+            .is_some_and(|max| now.sequence - disputed_at.sequence > max);
+        too_old || too_long
+    }
+}
+
+#[derive(Debug, serde::Serialize, derive_new::new)]
+pub struct ExpiredDispute {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub action: ExpiryAction,
+    pub amount: Amount,
+}
+
+/// Resolves or charges back stale disputes, as requested by the policy.
+/// Moved funds and locked accounts are counted in the statistics.
+pub fn expire_stale_disputes(
+    db: &mut MemDatabase,
+    policy: &ExpiryPolicy,
+    statistics: &mut Statistics,
+    record_statements: bool,
+) -> Vec<ExpiredDispute> {
+    let now = db.now();
+    let mut report = Vec::new();
+
+    for (client_id, account) in db.accounts_mut() {
+        let stale: Vec<_> = account
+            .disputed_transfers()
+            .filter(|(_, transfer)| {
+                let disputed_at = transfer.disputed_at.unwrap_or(now);
+                policy.is_stale(disputed_at, now)
+            })
+            .map(|(transaction_id, transfer)| (*transaction_id, transfer.amount_disputed))
+            .collect();
+
+        for (transaction_id, amount) in stale {
+            let total_before = account.amount_total;
+            let locked_before = account.locked;
+            let (transaction_type, outcome) = match policy.action {
+                ExpiryAction::Resolve => (
+                    "resolve",
+                    Resolve::new(transaction_id, None).execute(account),
+                ),
+                ExpiryAction::Chargeback => (
+                    "chargeback",
+                    Chargeback::new(transaction_id, None).execute(account),
+                ),
+            };
+            if let Err(err) = outcome {
+                log::error!("Stale dispute not expired: {:?}", err);
+                continue;
+            }
+            statistics.count_funds(transaction_type, account.amount_total - total_before);
+            if account.locked && !locked_before {
+                statistics.accounts_locked += 1;
+            }
+            if record_statements {
+                account.record_statement(
+                    *client_id,
+                    now,
+                    transaction_type,
+                    transaction_id,
+                    total_before,
+                );
+            }
+            log::info!(
+                "Stale dispute expired with {:?}, client ID: {}, transaction: {}",
+                policy.action,
+                client_id,
+                transaction_id
+            );
+            report.push(ExpiredDispute::new(
+                *client_id,
+                transaction_id,
+                policy.action,
+                amount,
+            ));
+        }
+    }
+
+    report.sort_by_key(|expired| (expired.client, expired.tx));
+    report
+}
@@ -0,0 +1,148 @@
+use crate::database::{Account, MemDatabase};
+use crate::statistics::Statistics;
+use crate::transactions::{Interest, Transaction};
+use crate::transport::record::{Amount, ClientId, TransactionId, AMOUNT_SCALE};
+use std::collections::HashMap;
+
+const DAYS_PER_YEAR: u64 = 365;
+
+/// Annual interest rates are fractions, for instance 0.02 for 2%.
+#[derive(Debug, Clone)]
+pub struct InterestPolicy {
+    /// Rate of accounts with no tier or with a tier not listed in `tier_rates`
+    pub annual_rate: Amount,
+    pub tier_rates: HashMap<String, Amount>,
+    /// Length of the accrual period
+    pub days: u64,
+    /// Highest ID under which interest is recorded in history of each account.
+    /// Every accrual takes the highest ID not yet used by the account.
+    pub transaction_id: TransactionId,
+}
+
+impl InterestPolicy {
+    fn rate(&self, tier: Option<&str>) -> Amount {
+        tier.and_then(|tier| self.tier_rates.get(tier))
+            .copied()
+            .unwrap_or(self.annual_rate)
+    }
+
+    fn interest(&self, balance: Amount, rate: Amount) -> Amount {
+        let interest = balance * rate * Amount::from(self.days) / Amount::from(DAYS_PER_YEAR);
+        interest.trunc_with_scale(AMOUNT_SCALE)
+    }
+
+    /// ID of the next accrual, distinct from IDs in history of the account
+    fn transaction_id(&self, account: &Account) -> Option<TransactionId> {
+        (0..=self.transaction_id)
+            .rev()
+            .find(|transaction_id| !account.contains_transfer(transaction_id))
+    }
+}
+
+#[derive(Debug, serde::Serialize, derive_new::new)]
+pub struct InterestPayment {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub tier: Option<String>,
+    pub rate: Amount,
+    pub amount: Amount,
+}
+
+/// Payments ordered by client, with their total
+#[derive(Debug, Default)]
+pub struct InterestReport {
+    pub payments: Vec<InterestPayment>,
+    pub total: Amount,
+}
+
+/// Line of the report file. The last one gives the total, without client.
+#[derive(serde::Serialize)]
+pub struct InterestReportRow<'a> {
+    client: Option<ClientId>,
+    tx: Option<TransactionId>,
+    tier: Option<&'a str>,
+    rate: Option<Amount>,
+    amount: Amount,
+}
+
+impl InterestReport {
+    pub fn rows(&self) -> Vec<InterestReportRow<'_>> {
+        let payments = self.payments.iter().map(|payment| InterestReportRow {
+            client: Some(payment.client),
+            tx: Some(payment.tx),
+            tier: payment.tier.as_deref(),
+            rate: Some(payment.rate),
+            amount: payment.amount,
+        });
+        let total = InterestReportRow {
+            client: None,
+            tx: None,
+            tier: None,
+            rate: None,
+            amount: self.total,
+        };
+        payments.chain(std::iter::once(total)).collect()
+    }
+}
+
+/// Credits interest on positive available funds of accounts that are not closed.
+/// Paid interest is counted in the statistics.
+pub fn accrue_interest(
+    db: &mut MemDatabase,
+    policy: &InterestPolicy,
+    statistics: &mut Statistics,
+    record_statements: bool,
+) -> InterestReport {
+    let now = db.now();
+    let mut payments = Vec::new();
+
+    for (client_id, account) in db.accounts_mut() {
+        if account.closed {
+            continue;
+        }
+        let rate = policy.rate(account.tier.as_deref());
+        let amount = policy.interest(account.amount_available(), rate);
+        if amount <= Amount::ZERO {
+            continue;
+        }
+
+        let Some(transaction_id) = policy.transaction_id(account) else {
+            log::error!(
+                "Interest not credited, no free ID, client ID: {}",
+                client_id
+            );
+            continue;
+        };
+        let total_before = account.amount_total;
+        let interest = Interest::new(transaction_id, amount, now.timestamp);
+        if let Err(err) = interest.execute(account) {
+            log::error!("Interest not credited, client ID: {}: {:?}", client_id, err);
+            continue;
+        }
+        if record_statements {
+            account.record_statement(*client_id, now, "interest", transaction_id, total_before);
+        }
+        log::info!(
+            "Interest credited, client ID: {}, amount: {}",
+            client_id,
+            amount
+        );
+        payments.push(InterestPayment::new(
+            *client_id,
+            transaction_id,
+            account.tier.clone(),
+            rate,
+            amount,
+        ));
+    }
+
+    payments.sort_by_key(|payment| payment.client);
+    let total: Amount = payments.iter().map(|payment| payment.amount).sum();
+    statistics.count_funds("interest", total);
+    log::info!(
+        "Interest paid to {} accounts, total: {}",
+        payments.len(),
+        total
+    );
+    InterestReport { payments, total }
+}
@@ -0,0 +1,9 @@
+mod expiry;
+mod interest;
+mod reconcile;
+mod summary;
+
+pub use crate::operations::expiry::{expire_stale_disputes, ExpiryAction, ExpiryPolicy};
+pub use crate::operations::interest::{accrue_interest, InterestPolicy};
+pub use crate::operations::reconcile::reconcile;
+pub use crate::operations::summary::{summarize, Summary};
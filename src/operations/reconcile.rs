@@ -0,0 +1,60 @@
+use crate::database::MemDatabase;
+use crate::transport::record::{Amount, ClientId};
+use crate::transport::ExpectedAccount;
+use std::collections::HashSet;
+
+#[derive(Debug, serde::Serialize, derive_new::new)]
+pub struct Difference {
+    pub client: ClientId,
+    pub field: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Compares accounts of the database with the expected ones. Amounts are compared after
+/// rounding to `scale` digits past the decimal point.
+pub fn reconcile(db: &MemDatabase, expected: &[ExpectedAccount], scale: u32) -> Vec<Difference> {
+    let mut report = Vec::new();
+    let same_amount =
+        |expected: Amount, actual: Amount| expected.round_dp(scale) == actual.round_dp(scale);
+
+    for row in expected {
+        let Some(account) = db.accounts().get(&row.client) else {
+            let diff = Difference::new(row.client, "account", "present".into(), "missing".into());
+            report.push(diff);
+            continue;
+        };
+        let amounts = [
+            ("available", row.available, account.amount_available()),
+            ("held", row.held, account.amount_held),
+            ("total", row.total, account.amount_total),
+        ];
+        for (field, expected, actual) in amounts {
+            if !same_amount(expected, actual) {
+                let diff =
+                    Difference::new(row.client, field, expected.to_string(), actual.to_string());
+                report.push(diff);
+            }
+        }
+        if row.locked != account.locked {
+            let diff = Difference::new(
+                row.client,
+                "locked",
+                row.locked.to_string(),
+                account.locked.to_string(),
+            );
+            report.push(diff);
+        }
+    }
+
+    let expected_clients: HashSet<_> = expected.iter().map(|row| row.client).collect();
+    for client_id in db.accounts().keys() {
+        if !expected_clients.contains(client_id) {
+            let diff = Difference::new(*client_id, "account", "missing".into(), "present".into());
+            report.push(diff);
+        }
+    }
+
+    report.sort_by_key(|diff| diff.client);
+    report
+}
@@ -0,0 +1,38 @@
+use crate::database::MemDatabase;
+use crate::transport::record::Amount;
+use std::fmt;
+
+/// Totals over all accounts of the database.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct Summary {
+    pub accounts: usize,
+    pub locked: usize,
+    pub closed: usize,
+    pub available: Amount,
+    pub held: Amount,
+    pub total: Amount,
+}
+
+pub fn summarize(db: &MemDatabase) -> Summary {
+    let mut summary = Summary::default();
+    for account in db.accounts().values() {
+        summary.accounts += 1;
+        summary.locked += usize::from(account.locked);
+        summary.closed += usize::from(account.closed);
+        summary.available += account.amount_available();
+        summary.held += account.amount_held;
+        summary.total += account.amount_total;
+    }
+    summary
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "accounts:         {}", self.accounts)?;
+        writeln!(f, "locked:           {}", self.locked)?;
+        writeln!(f, "closed:           {}", self.closed)?;
+        writeln!(f, "available:        {}", self.available)?;
+        writeln!(f, "held:             {}", self.held)?;
+        writeln!(f, "total:            {}", self.total)
+    }
+}
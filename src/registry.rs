@@ -0,0 +1,162 @@
+use crate::config::Config;
+use crate::database::Moment;
+use crate::transactions::{
+    Adjustment, AdjustmentKind, Authorize, Capture, Chargeback, Close, Deposit, Dispute, Open,
+    Resolve, Reversal, SetLimit, Transaction, TransactionError, Void, Withdrawal,
+};
+use crate::transport::record::Record;
+use std::collections::HashMap;
+
+/// Circumstances of the record, available to constructors of transactions
+pub struct Context<'a> {
+    pub now: Moment,
+    pub config: &'a Config,
+}
+
+pub type Constructor =
+    Box<dyn Fn(&Record, &Context) -> Result<Box<dyn Transaction>, TransactionError>>;
+
+/// Maps names of transaction types, as given in the `type` column, to constructors of transactions.
+/// Types are registered by their canonical names, which may have aliases.
+pub struct TransactionRegistry {
+    constructors: HashMap<String, Constructor>,
+    /// Alternative names mapped to canonical names
+    aliases: HashMap<String, String>,
+    case_insensitive: bool,
+}
+
+impl TransactionRegistry {
+    /// Registry without any types, see `default()` for the built-in ones.
+    pub fn new() -> Self {
+        Self {
+            constructors: HashMap::new(),
+            aliases: HashMap::new(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Registers the type, replacing constructor of the type with the same name.
+    pub fn with_type<F>(mut self, name: &str, constructor: F) -> Self
+    where
+        F: Fn(&Record, &Context) -> Result<Box<dyn Transaction>, TransactionError> + 'static,
+    {
+        self.constructors
+            .insert(name.to_string(), Box::new(constructor));
+        self
+    }
+
+    pub fn with_alias(mut self, alias: &str, name: &str) -> Self {
+        self.aliases.insert(alias.to_string(), name.to_string());
+        self
+    }
+
+    /// Matches names and aliases regardless of their case.
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self
+    }
+
+    /// Canonical name of the type given by its name or alias.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        if let Some((name, _)) = self.constructors.get_key_value(name) {
+            return Some(name);
+        }
+        if let Some(name) = self.aliases.get(name) {
+            return Some(name);
+        }
+        if !self.case_insensitive {
+            return None;
+        }
+        let names = self.constructors.keys().map(|name| (name, name));
+        names
+            .chain(self.aliases.iter())
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, name)| name.as_str())
+    }
+
+    pub fn construct(
+        &self,
+        rec: &Record,
+        context: &Context,
+    ) -> Result<Box<dyn Transaction>, TransactionError> {
+        let constructor = self
+            .resolve(&rec.r#type)
+            .and_then(|name| self.constructors.get(name));
+        let Some(constructor) = constructor else {
+            let detail = format!("{:?}", rec.r#type);
+            return Err(TransactionError::reject("Invalid transaction type").with_detail(detail));
+        };
+        constructor(rec, context)
+    }
+}
+
+impl Default for TransactionRegistry {
+    /// Registry of the built-in types.
+    fn default() -> Self {
+        Self::new()
+            .with_type("deposit", |rec, ctx| {
+                let timestamp = ctx.now.timestamp;
+                Ok(Box::new(Deposit::new(rec.tx, rec.amount()?, timestamp)))
+            })
+            .with_type("withdrawal", |rec, ctx| {
+                let timestamp = ctx.now.timestamp;
+                Ok(Box::new(Withdrawal::new(rec.tx, rec.amount()?, timestamp)))
+            })
+            .with_type("dispute", |rec, ctx| {
+                let window = ctx.config.dispute_window;
+                let amount = rec.optional_amount()?;
+                Ok(Box::new(Dispute::new(rec.tx, amount, ctx.now, window)))
+            })
+            .with_type("resolve", |rec, _| {
+                Ok(Box::new(Resolve::new(rec.tx, rec.optional_amount()?)))
+            })
+            .with_type("chargeback", |rec, _| {
+                Ok(Box::new(Chargeback::new(rec.tx, rec.optional_amount()?)))
+            })
+            .with_type("adjust_credit", |rec, ctx| {
+                adjustment(rec, ctx, AdjustmentKind::Credit)
+            })
+            .with_type("adjust_debit", |rec, ctx| {
+                adjustment(rec, ctx, AdjustmentKind::Debit)
+            })
+            .with_type("reversal", |rec, ctx| {
+                let overdraft_allowed = ctx.config.reversal_overdraft;
+                Ok(Box::new(Reversal::new(rec.tx, overdraft_allowed)))
+            })
+            .with_type("authorize", |rec, _| {
+                Ok(Box::new(Authorize::new(rec.tx, rec.amount()?)))
+            })
+            .with_type("capture", |rec, ctx| {
+                let timestamp = ctx.now.timestamp;
+                let amount = rec.optional_amount()?;
+                Ok(Box::new(Capture::new(rec.tx, amount, timestamp)))
+            })
+            .with_type("void", |rec, _| Ok(Box::new(Void::new(rec.tx))))
+            .with_type("open", |rec, _| {
+                let tier = rec.tier.clone();
+                let currency = rec.currency.clone();
+                let credit_limit = rec.optional_non_negative_amount()?;
+                Ok(Box::new(Open::new(tier, currency, credit_limit)))
+            })
+            .with_type("close", |rec, ctx| {
+                Ok(Box::new(Close::new(rec.tx, ctx.now.timestamp)))
+            })
+            .with_type("set_limit", |rec, _| {
+                let credit_limit = rec.non_negative_amount()?;
+                Ok(Box::new(SetLimit::new(credit_limit)))
+            })
+    }
+}
+
+fn adjustment(
+    rec: &Record,
+    ctx: &Context,
+    kind: AdjustmentKind,
+) -> Result<Box<dyn Transaction>, TransactionError> {
+    rec.reason_code()?;
+    let timestamp = ctx.now.timestamp;
+    let allowed_on_frozen = ctx.config.adjustments_on_frozen;
+    let amount = rec.amount()?;
+    let adjustment = Adjustment::new(rec.tx, kind, amount, timestamp, allowed_on_frozen);
+    Ok(Box::new(adjustment))
+}
@@ -0,0 +1,170 @@
+use crate::transactions::TransactionError;
+use crate::transport::record::{Amount, SourcePosition};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeKind {
+    Completed,
+    Denied,
+    Rejected,
+    LimitExceeded,
+}
+
+impl OutcomeKind {
+    pub fn of(outcome: &Result<(), TransactionError>) -> Self {
+        match outcome {
+            Ok(()) => OutcomeKind::Completed,
+            Err(TransactionError::Denied(_)) => OutcomeKind::Denied,
+            Err(TransactionError::Rejected(_)) => OutcomeKind::Rejected,
+            Err(TransactionError::LimitExceeded(_)) => OutcomeKind::LimitExceeded,
+        }
+    }
+
+    /// Identifier of the outcome, as serialized
+    pub fn key(&self) -> &'static str {
+        match self {
+            OutcomeKind::Completed => "completed",
+            OutcomeKind::Denied => "denied",
+            OutcomeKind::Rejected => "rejected",
+            OutcomeKind::LimitExceeded => "limit_exceeded",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            OutcomeKind::Completed => "completed",
+            OutcomeKind::Denied => "denied",
+            OutcomeKind::Rejected => "rejected",
+            OutcomeKind::LimitExceeded => "limit exceeded",
+        }
+    }
+}
+
+/// Records that ended with the same outcome for the same reason.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OutcomeCount {
+    pub outcome: OutcomeKind,
+    pub reason: String,
+    pub count: u64,
+    /// Number of the first such record, starting from 1
+    pub first_record: u64,
+    /// Place of the first such record in the input
+    pub first_position: Option<SourcePosition>,
+}
+
+/// Counters collected by `Dispatcher` while processing records.
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct Statistics {
+    pub records: u64,
+    /// Records by their type, malformed records are not included
+    pub types: BTreeMap<String, u64>,
+    pub completed: u64,
+    pub denied: u64,
+    pub rejected: u64,
+    pub limit_exceeded: u64,
+    /// Records of rolled back batches, reported by the outcome of their commit instead
+    pub rolled_back: u64,
+    #[serde(serialize_with = "serialize_outcomes")]
+    outcomes: BTreeMap<(OutcomeKind, String), OutcomeCount>,
+    pub accounts_created: u64,
+    pub accounts_locked: u64,
+    pub deposited: Amount,
+    /// Funds taken by withdrawals and captures
+    pub withdrawn: Amount,
+    pub charged_back: Amount,
+    /// Net change of funds by manual adjustments
+    pub adjusted: Amount,
+    /// Interest credited at the end of the batch
+    pub interest_paid: Amount,
+}
+
+impl Statistics {
+    pub fn count_outcome(
+        &mut self,
+        transaction_type: Option<&str>,
+        position: Option<&SourcePosition>,
+        outcome: &Result<(), TransactionError>,
+    ) {
+        self.records += 1;
+        if let Some(transaction_type) = transaction_type {
+            *self.types.entry(transaction_type.to_string()).or_default() += 1;
+        }
+        let kind = OutcomeKind::of(outcome);
+        let reason = outcome.as_ref().err().map_or("", TransactionError::reason);
+        *match kind {
+            OutcomeKind::Completed => &mut self.completed,
+            OutcomeKind::Denied => &mut self.denied,
+            OutcomeKind::Rejected => &mut self.rejected,
+            OutcomeKind::LimitExceeded => &mut self.limit_exceeded,
+        } += 1;
+
+        let records = self.records;
+        let entry = self.outcomes.entry((kind, reason.to_string()));
+        entry
+            .or_insert_with(|| OutcomeCount {
+                outcome: kind,
+                reason: reason.to_string(),
+                count: 0,
+                first_record: records,
+                first_position: position.cloned(),
+            })
+            .count += 1;
+    }
+
+    /// Counts funds moved by a completed transaction, given the change of total funds.
+    pub fn count_funds(&mut self, transaction_type: &str, change: Amount) {
+        match transaction_type {
+            "deposit" => self.deposited += change,
+            "withdrawal" | "capture" => self.withdrawn -= change,
+            "chargeback" => self.charged_back -= change,
+            "adjust_credit" | "adjust_debit" => self.adjusted += change,
+            "interest" => self.interest_paid += change,
+            _ => {}
+        }
+    }
+
+    /// Outcomes grouped by reason, ordered by kind of the outcome.
+    pub fn outcomes(&self) -> impl Iterator<Item = &OutcomeCount> {
+        self.outcomes.values()
+    }
+}
+
+fn serialize_outcomes<S: serde::Serializer>(
+    outcomes: &BTreeMap<(OutcomeKind, String), OutcomeCount>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(outcomes.values())
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "records:          {}", self.records)?;
+        for (transaction_type, count) in self.types.iter() {
+            writeln!(f, "  {:<16}{}", format!("{}:", transaction_type), count)?;
+        }
+        writeln!(f, "completed:        {}", self.completed)?;
+        writeln!(f, "denied:           {}", self.denied)?;
+        writeln!(f, "rejected:         {}", self.rejected)?;
+        writeln!(f, "limit exceeded:   {}", self.limit_exceeded)?;
+        writeln!(f, "rolled back:      {}", self.rolled_back)?;
+        for outcome in self.outcomes() {
+            if outcome.outcome != OutcomeKind::Completed {
+                let kind = outcome.outcome.name();
+                write!(f, "  {} ({}): {}", outcome.reason, kind, outcome.count)?;
+                match &outcome.first_position {
+                    Some(position) => writeln!(f, ", first at {}", position)?,
+                    None => writeln!(f)?,
+                }
+            }
+        }
+        writeln!(f, "accounts created: {}", self.accounts_created)?;
+        writeln!(f, "accounts locked:  {}", self.accounts_locked)?;
+        writeln!(f, "deposited:        {}", self.deposited)?;
+        writeln!(f, "withdrawn:        {}", self.withdrawn)?;
+        writeln!(f, "charged back:     {}", self.charged_back)?;
+        writeln!(f, "adjusted:         {}", self.adjusted)?;
+        writeln!(f, "interest paid:    {}", self.interest_paid)
+    }
+}
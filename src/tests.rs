@@ -1,22 +1,59 @@
 #[cfg(test)]
+#[allow(clippy::module_inception, clippy::bool_assert_comparison)]
 mod tests {
+    use clap::{CommandFactory, Parser};
     use rust_decimal::prelude::FromPrimitive;
     use rust_decimal::Decimal;
+    use std::path::PathBuf;
 
-    use crate::database::{Account, MemDatabase};
+    use crate::cli::{BatchOptions, Cli, Command};
+    use crate::commands::batch::{check_errors, run_batch};
+    use crate::commands::BatchAborted;
+    use crate::config::{self, Config, VelocityLimits};
+    use crate::database::{
+        Account, IdempotencyStore, MemDatabase, Moment, Transfer, TransferKind, TransferState,
+    };
     use crate::dispatcher::Dispatcher;
-    use crate::transport::record::{ClientId, Record, TransactionId};
+    use crate::fraud::{AccountBurst, RapidWithdrawal, RepeatedDisputes};
+    use crate::logging;
+    use crate::metrics::SharedMetrics;
+    use crate::operations::{self, ExpiryAction, ExpiryPolicy, InterestPolicy};
+    use crate::registry::TransactionRegistry;
+    use crate::statistics::{OutcomeKind, Statistics};
+    use crate::transactions::{Transaction, TransactionError};
+    use crate::transport::record::{ClientId, Record, SourcePosition, TransactionId};
+    use crate::transport::{CsvExporter, CsvImporter, ExpectedAccount, ImportError};
 
     // Test Framework
 
+    fn record<T: Into<String>, U: Into<Option<i32>>>(
+        transaction_type: T,
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        amount: U,
+    ) -> Result<Record, ImportError> {
+        let amount = match amount.into() {
+            Some(x) => Decimal::from_i32(x),
+            None => None,
+        };
+        Ok(Record::new(
+            transaction_type.into(),
+            client_id,
+            transaction_id,
+            amount,
+        ))
+    }
+
     struct TestApp {
         pub db: MemDatabase,
+        pub config: Config,
     }
 
     impl TestApp {
         fn new() -> Self {
             let db = MemDatabase::new();
-            Self { db }
+            let config = Config::default();
+            Self { db, config }
         }
         fn dispatch<T: Into<String>, U: Into<Option<i32>>>(
             &mut self,
@@ -25,18 +62,25 @@ mod tests {
             transaction_id: TransactionId,
             amount: U,
         ) {
-            let amount = match amount.into() {
-                Some(x) => Decimal::from_i32(x),
-                None => None,
-            };
+            let row = record(transaction_type, client_id, transaction_id, amount);
+            let mut dispatcher = Dispatcher::new(&mut self.db).with_config(self.config.clone());
+            dispatcher.dispatch(&row);
+        }
 
-            let mut dispatcher = Dispatcher::new(&mut self.db);
-            dispatcher.dispatch(&Ok(Record::new(
-                transaction_type.into(),
-                client_id,
-                transaction_id,
-                amount.into(),
-            )));
+        fn dispatch_on_day<T: Into<String>, U: Into<Option<i32>>>(
+            &mut self,
+            day: u64,
+            transaction_type: T,
+            client_id: ClientId,
+            transaction_id: TransactionId,
+            amount: U,
+        ) {
+            let mut row = record(transaction_type, client_id, transaction_id, amount);
+            if let Ok(rec) = &mut row {
+                rec.timestamp = Some(day * config::SECONDS_PER_DAY);
+            }
+            let mut dispatcher = Dispatcher::new(&mut self.db).with_config(self.config.clone());
+            dispatcher.dispatch(&row);
         }
 
         fn first_account(&mut self) -> &Account {
@@ -286,31 +330,1753 @@ mod tests {
         assert_eq!(ta.first_account().locked, true);
     }
 
-    // Mixed
+    // Partial Dispute
 
     #[test]
-    fn test_deposit_on_frozen_account_possible() {
+    fn test_partial_dispute_holds_only_disputed_amount() {
         let mut ta = TestApp::new();
         ta.dispatch("deposit", 10, 100, 200);
-        ta.dispatch("deposit", 10, 101, 100);
+        ta.dispatch("dispute", 10, 100, 50);
+        ta.assert_first_account_total(200);
+        ta.assert_first_account_held(50);
+    }
+
+    #[test]
+    fn test_partial_disputes_are_allowed_up_to_transfer_amount() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 50);
+        ta.dispatch("dispute", 10, 100, 100);
+        ta.dispatch("dispute", 10, 100, 60);
+        ta.assert_first_account_held(150);
         ta.dispatch("dispute", 10, 100, None);
-        ta.dispatch("chargeback", 10, 100, None);
-        ta.dispatch("deposit", 10, 102, 300);
-        ta.assert_first_account_total(400);
+        ta.assert_first_account_held(200);
+        ta.dispatch("dispute", 10, 100, 10);
+        ta.assert_first_account_held(200);
+    }
+
+    #[test]
+    fn test_partial_resolve_releases_only_resolved_amount() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 150);
+        ta.dispatch("resolve", 10, 100, 100);
+        ta.assert_first_account_held(50);
+        ta.dispatch("resolve", 10, 100, 60);
+        ta.assert_first_account_held(50);
+        ta.dispatch("resolve", 10, 100, None);
         ta.assert_first_account_held(00);
-        assert_eq!(ta.first_account().locked, true);
+        ta.assert_first_account_total(200);
     }
 
     #[test]
-    fn test_withdrawal_from_frozen_account_not_possible() {
+    fn test_partial_chargeback_subtracts_only_charged_back_amount() {
         let mut ta = TestApp::new();
         ta.dispatch("deposit", 10, 100, 200);
-        ta.dispatch("deposit", 10, 101, 100);
+        ta.dispatch("dispute", 10, 100, 80);
+        ta.dispatch("chargeback", 10, 100, 30);
+        ta.assert_first_account_total(170);
+        ta.assert_first_account_held(50);
+        assert!(ta.first_account().locked);
+    }
+
+    #[test]
+    fn test_chargeback_exceeding_disputed_amount_is_denied() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 80);
+        ta.dispatch("chargeback", 10, 100, 100);
+        ta.assert_first_account_total(200);
+        ta.assert_first_account_held(80);
+        assert!(!ta.first_account().locked);
+    }
+
+    // Transfer State
+
+    #[test]
+    fn test_charged_back_transfer_is_kept_and_cannot_be_disputed_again() {
+        let mut transfer = Transfer::new(TransferKind::Deposit, Decimal::from(100), None);
+        assert_eq!(
+            transfer.dispute(None, Decimal::from(100), Moment::default()),
+            Ok(Decimal::from(100))
+        );
+        assert_eq!(transfer.charge_back(None), Ok(Decimal::from(100)));
+        let cause = "Corresponding transfer already charged back";
+        assert_eq!(
+            transfer.dispute(None, Decimal::from(100), Moment::default()),
+            Err(TransactionError::deny(cause))
+        );
+        assert_eq!(
+            transfer.charge_back(None),
+            Err(TransactionError::deny(cause))
+        );
+        assert_eq!(transfer.resolve(None), Err(TransactionError::deny(cause)));
+    }
+
+    #[test]
+    fn test_settled_and_resolved_transfers_cannot_be_resolved_or_charged_back() {
+        let mut transfer = Transfer::new(TransferKind::Deposit, Decimal::from(100), None);
+        let cause = "Corresponding transfer not disputed";
+        assert_eq!(transfer.resolve(None), Err(TransactionError::deny(cause)));
+        transfer
+            .dispute(None, Decimal::from(100), Moment::default())
+            .unwrap();
+        transfer.resolve(None).unwrap();
+        assert_eq!(transfer.resolve(None), Err(TransactionError::deny(cause)));
+        assert_eq!(
+            transfer.charge_back(None),
+            Err(TransactionError::deny(cause))
+        );
+    }
+
+    #[test]
+    fn test_partially_charged_back_transfer_remains_disputed() {
+        let mut transfer = Transfer::new(TransferKind::Deposit, Decimal::from(100), None);
+        transfer
+            .dispute(
+                Some(Decimal::from(60)),
+                Decimal::from(100),
+                Moment::default(),
+            )
+            .unwrap();
+        transfer.charge_back(Some(Decimal::from(20))).unwrap();
+        assert_eq!(transfer.resolve(None), Ok(Decimal::from(40)));
+        let cause = "Corresponding transfer not disputed";
+        assert_eq!(
+            transfer.charge_back(None),
+            Err(TransactionError::deny(cause))
+        );
+    }
+
+    // Dispute Time Limits
+
+    #[test]
+    fn test_dispute_within_window_is_allowed() {
+        let mut ta = TestApp::new();
+        ta.config.dispute_window = Some(config::days(120));
+        ta.dispatch_on_day(1, "deposit", 10, 100, 200);
+        ta.dispatch_on_day(121, "dispute", 10, 100, None);
+        ta.assert_first_account_held(200);
+    }
+
+    #[test]
+    fn test_dispute_after_window_is_denied() {
+        let mut ta = TestApp::new();
+        ta.config.dispute_window = Some(config::days(120));
+        ta.dispatch_on_day(1, "deposit", 10, 100, 200);
+        ta.dispatch_on_day(122, "deposit", 10, 101, 100);
         ta.dispatch("dispute", 10, 100, None);
-        ta.dispatch("chargeback", 10, 100, None);
-        ta.dispatch("withdrawal", 10, 200, 50);
+        ta.assert_first_account_held(00);
+        ta.dispatch("dispute", 10, 101, None);
+        ta.assert_first_account_held(100);
+    }
+
+    #[test]
+    fn test_dispute_past_deadline_is_resolved_automatically() {
+        let mut ta = TestApp::new();
+        ta.config.dispute_deadline = Some(config::days(30));
+        ta.dispatch_on_day(1, "deposit", 10, 100, 200);
+        ta.dispatch_on_day(2, "dispute", 10, 100, None);
+        ta.dispatch_on_day(32, "deposit", 10, 101, 100);
+        ta.assert_first_account_held(200);
+        ta.dispatch_on_day(33, "chargeback", 10, 100, None);
+        ta.assert_first_account_total(300);
+        ta.assert_first_account_held(00);
+        assert!(!ta.first_account().locked);
+    }
+
+    #[test]
+    fn test_dispute_past_deadline_is_resolved_by_record_of_other_client() {
+        let mut ta = TestApp::new();
+        ta.config.record_statements = true;
+        ta.config.dispute_deadline = Some(config::days(30));
+        ta.dispatch_on_day(1, "deposit", 10, 100, 200);
+        ta.dispatch_on_day(2, "dispute", 10, 100, None);
+        ta.dispatch_on_day(32, "deposit", 20, 200, 100);
+        assert_eq!(ta.db.accounts()[&10].amount_held, Decimal::from(200));
+        ta.dispatch_on_day(33, "deposit", 20, 201, 100);
+        assert_eq!(ta.db.accounts()[&10].amount_held, Decimal::ZERO);
+        assert_eq!(ta.db.accounts()[&10].statement().len(), 3);
+    }
+
+    #[test]
+    fn test_dispute_days_beyond_duration_range_are_rejected() {
+        let max = config::MAX_DAYS.to_string();
+        let over = (config::MAX_DAYS + 1).to_string();
+        for option in [
+            "--dispute-window-days",
+            "--dispute-deadline-days",
+            "--expire-disputes-after-days",
+        ] {
+            assert!(Cli::try_parse_from(["bank", option, &max, "input.csv"]).is_ok());
+            assert!(Cli::try_parse_from(["bank", option, &over, "input.csv"]).is_err());
+        }
+        assert!(config::days(config::MAX_DAYS).as_secs() > 0);
+    }
+
+    // Stale Dispute Expiry
+
+    #[test]
+    fn test_stale_disputes_are_resolved_at_end_of_batch() {
+        let mut ta = TestApp::new();
+        ta.dispatch_on_day(1, "deposit", 10, 100, 200);
+        ta.dispatch_on_day(2, "deposit", 10, 101, 100);
+        ta.dispatch_on_day(3, "dispute", 10, 100, None);
+        ta.dispatch_on_day(9, "dispute", 10, 101, None);
+        ta.dispatch_on_day(10, "deposit", 20, 200, 100);
+        let policy = ExpiryPolicy {
+            max_age: Some(config::days(5)),
+            max_transactions: None,
+            action: ExpiryAction::Resolve,
+        };
+        let report = operations::expire_stale_disputes(
+            &mut ta.db,
+            &policy,
+            &mut Statistics::default(),
+            false,
+        );
+        assert_eq!(report.len(), 1);
+        assert_eq!((report[0].client, report[0].tx), (10, 100));
+        assert_eq!(report[0].amount, Decimal::from(200));
+        assert_eq!(ta.db.accounts()[&10].amount_held, Decimal::from(100));
+        assert_eq!(ta.db.accounts()[&10].amount_total, Decimal::from(300));
+    }
+
+    #[test]
+    fn test_stale_disputes_are_charged_back_after_number_of_transactions() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 50);
+        ta.dispatch("deposit", 10, 101, 10);
+        ta.dispatch("deposit", 10, 102, 10);
+        let policy = ExpiryPolicy {
+            max_age: None,
+            max_transactions: Some(2),
+            action: ExpiryAction::Chargeback,
+        };
+        let mut statistics = Statistics::default();
+        assert!(
+            operations::expire_stale_disputes(&mut ta.db, &policy, &mut statistics, false)
+                .is_empty()
+        );
+        ta.dispatch("deposit", 10, 103, 10);
+        assert_eq!(
+            operations::expire_stale_disputes(&mut ta.db, &policy, &mut statistics, false).len(),
+            1
+        );
+        assert_eq!(statistics.charged_back, Decimal::from(50));
+        assert_eq!(statistics.accounts_locked, 1);
+        ta.assert_first_account_total(180);
+        ta.assert_first_account_held(00);
+        assert!(ta.first_account().locked);
+    }
+
+    // Authorization
+
+    #[test]
+    fn test_authorization_holds_funds() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("authorize", 10, 300, 150);
+        ta.dispatch("withdrawal", 10, 200, 100);
+        ta.assert_first_account_total(200);
+        ta.assert_first_account_held(150);
+    }
+
+    #[test]
+    fn test_authorization_exceeding_available_funds_is_denied() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 100);
+        ta.dispatch("authorize", 10, 300, 150);
+        ta.assert_first_account_held(100);
+    }
+
+    #[test]
+    fn test_full_capture_withdraws_authorized_funds() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("authorize", 10, 300, 150);
+        ta.dispatch("capture", 10, 300, None);
+        ta.assert_first_account_total(50);
+        ta.assert_first_account_held(00);
+    }
+
+    #[test]
+    fn test_partial_capture_releases_remaining_hold() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("authorize", 10, 300, 150);
+        ta.dispatch("capture", 10, 300, 200);
+        ta.assert_first_account_held(150);
+        ta.dispatch("capture", 10, 300, 100);
         ta.assert_first_account_total(100);
         ta.assert_first_account_held(00);
-        assert_eq!(ta.first_account().locked, true);
+        ta.dispatch("capture", 10, 300, 10);
+        ta.assert_first_account_total(100);
+    }
+
+    #[test]
+    fn test_void_releases_hold_without_dispute_holds() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 50);
+        ta.dispatch("authorize", 10, 300, 100);
+        ta.assert_first_account_held(150);
+        ta.dispatch("void", 10, 300, None);
+        ta.dispatch("void", 10, 300, None);
+        ta.assert_first_account_total(200);
+        ta.assert_first_account_held(50);
+    }
+
+    #[test]
+    fn test_voided_authorization_cannot_be_authorized_again() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("authorize", 10, 300, 50);
+        ta.dispatch("void", 10, 300, None);
+        ta.dispatch("authorize", 10, 300, 50);
+        ta.assert_first_account_held(00);
+        ta.dispatch("capture", 10, 300, None);
+        ta.assert_first_account_total(200);
+    }
+
+    #[test]
+    fn test_authorization_reusing_transfer_id_is_rejected() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("authorize", 10, 100, 50);
+        ta.assert_first_account_held(00);
+        ta.dispatch("capture", 10, 100, None);
+        ta.assert_first_account_total(200);
+    }
+
+    #[test]
+    fn test_captured_authorization_cannot_be_authorized_again() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("authorize", 10, 300, 50);
+        ta.dispatch("capture", 10, 300, None);
+        ta.dispatch("authorize", 10, 300, 50);
+        ta.assert_first_account_total(150);
+        ta.assert_first_account_held(00);
+    }
+
+    #[test]
+    fn test_capture_can_be_reversed() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("authorize", 10, 300, 50);
+        ta.dispatch("capture", 10, 300, 30);
+        ta.assert_first_account_total(170);
+        ta.dispatch("reversal", 10, 300, None);
+        ta.assert_first_account_total(200);
+        ta.assert_first_account_held(00);
+    }
+
+    // Overdraft
+
+    #[test]
+    fn test_withdrawal_can_use_overdraft_up_to_credit_limit() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 100);
+        ta.dispatch("set_limit", 10, 500, 50);
+        ta.dispatch("withdrawal", 10, 200, 140);
+        ta.assert_first_account_total(-40);
+        assert_eq!(ta.first_account().headroom(), Decimal::from(10));
+        ta.dispatch("withdrawal", 10, 201, 20);
+        ta.assert_first_account_total(-40);
+    }
+
+    #[test]
+    fn test_credit_limit_cannot_be_lowered_below_used_overdraft() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 100);
+        ta.dispatch("set_limit", 10, 500, 50);
+        ta.dispatch("withdrawal", 10, 200, 130);
+        ta.dispatch("set_limit", 10, 501, 20);
+        assert_eq!(ta.first_account().credit_limit, Decimal::from(50));
+        ta.dispatch("deposit", 10, 101, 30);
+        ta.dispatch("set_limit", 10, 502, 0);
+        assert_eq!(ta.first_account().credit_limit, Decimal::from(0));
+    }
+
+    #[test]
+    fn test_credit_limit_cannot_be_set_for_nonexisting_account() {
+        let mut ta = TestApp::new();
+        ta.dispatch("set_limit", 10, 500, 50);
+        assert_eq!(ta.db.accounts().len(), 0);
+    }
+
+    #[test]
+    fn test_limit_columns_are_exported_on_request() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 100);
+        let export = |limits| {
+            let mut output = Vec::new();
+            CsvExporter::new(csv::Writer::from_writer(&mut output))
+                .with_limits(limits)
+                .dump_accounts(ta.db.accounts())
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        let default = export(false);
+        assert!(default.starts_with("client,available,held,total,locked\n"));
+        let with_limits = export(true);
+        assert!(with_limits.ends_with(",false,0.0000,100.0000\n"));
+    }
+
+    // Velocity Limits
+
+    #[test]
+    fn test_withdrawal_above_maximal_amount_exceeds_limits() {
+        let mut ta = TestApp::new();
+        ta.config.withdrawal_limits.max_amount = Some(Decimal::from(100));
+        let mut dp = Dispatcher::new(&mut ta.db).with_config(ta.config.clone());
+        dp.dispatch(&record("deposit", 10, 100, 500));
+        let outcome = dp.try_dispatch(&record("withdrawal", 10, 200, 150));
+        assert!(matches!(outcome, Err(TransactionError::LimitExceeded(_))));
+        dp.dispatch(&record("withdrawal", 10, 201, 100));
+        ta.assert_first_account_total(400);
+    }
+
+    #[test]
+    fn test_withdrawals_are_limited_by_total_and_count_within_window() {
+        let mut ta = TestApp::new();
+        ta.config.velocity_window = Some(config::days(1));
+        ta.config.withdrawal_limits.max_total = Some(Decimal::from(100));
+        ta.config.withdrawal_limits.max_count = Some(2);
+        ta.dispatch_on_day(1, "deposit", 10, 100, 500);
+        ta.dispatch_on_day(1, "withdrawal", 10, 200, 60);
+        ta.dispatch_on_day(1, "withdrawal", 10, 201, 50);
+        ta.dispatch_on_day(1, "withdrawal", 10, 202, 40);
+        ta.dispatch_on_day(1, "withdrawal", 10, 203, 10);
+        ta.assert_first_account_total(400);
+        ta.dispatch_on_day(3, "withdrawal", 10, 204, 50);
+        ta.dispatch_on_day(3, "withdrawal", 10, 205, 50);
+        ta.assert_first_account_total(300);
+    }
+
+    #[test]
+    fn test_captures_are_subject_to_velocity_limits() {
+        let mut ta = TestApp::new();
+        ta.config.withdrawal_limits.max_amount = Some(Decimal::from(100));
+        ta.config.withdrawal_limits.max_total = Some(Decimal::from(150));
+        let mut dp = Dispatcher::new(&mut ta.db).with_config(ta.config.clone());
+        dp.dispatch(&record("deposit", 10, 100, 500));
+        dp.dispatch(&record("authorize", 10, 300, 200));
+        let outcome = dp.try_dispatch(&record("capture", 10, 300, None));
+        assert!(matches!(outcome, Err(TransactionError::LimitExceeded(_))));
+        dp.dispatch(&record("capture", 10, 300, 100));
+        dp.dispatch(&record("authorize", 10, 301, 80));
+        let outcome = dp.try_dispatch(&record("capture", 10, 301, None));
+        assert!(matches!(outcome, Err(TransactionError::LimitExceeded(_))));
+        ta.assert_first_account_total(400);
+    }
+
+    #[test]
+    fn test_client_limits_override_global_limits() {
+        let mut ta = TestApp::new();
+        ta.config.withdrawal_limits.max_amount = Some(Decimal::from(100));
+        let client_limits = VelocityLimits {
+            max_amount: Some(Decimal::from(300)),
+            ..Default::default()
+        };
+        ta.config.client_withdrawal_limits.insert(20, client_limits);
+        ta.dispatch("deposit", 10, 100, 500);
+        ta.dispatch("deposit", 20, 101, 500);
+        ta.dispatch("withdrawal", 10, 200, 200);
+        ta.dispatch("withdrawal", 20, 201, 200);
+        assert_eq!(ta.db.accounts()[&10].amount_total, Decimal::from(500));
+        assert_eq!(ta.db.accounts()[&20].amount_total, Decimal::from(300));
+    }
+
+    #[test]
+    fn test_client_limits_keep_global_limits_not_overridden() {
+        let mut ta = TestApp::new();
+        ta.config.withdrawal_limits.max_amount = Some(Decimal::from(100));
+        let client_limits = VelocityLimits {
+            max_count: Some(2),
+            ..Default::default()
+        };
+        ta.config.client_withdrawal_limits.insert(20, client_limits);
+        ta.dispatch("deposit", 20, 100, 500);
+        ta.dispatch("withdrawal", 20, 200, 200);
+        ta.dispatch("withdrawal", 20, 201, 50);
+        ta.dispatch("withdrawal", 20, 202, 50);
+        ta.dispatch("withdrawal", 20, 203, 50);
+        ta.assert_first_account_total(400);
+    }
+
+    #[test]
+    fn test_velocity_window_beyond_duration_range_is_rejected() {
+        let max = config::MAX_HOURS.to_string();
+        let over = (config::MAX_HOURS + 1).to_string();
+        let option = "--velocity-window-hours";
+        assert!(Cli::try_parse_from(["bank", option, &max, "input.csv"]).is_ok());
+        assert!(Cli::try_parse_from(["bank", option, &over, "input.csv"]).is_err());
+        assert!(config::hours(config::MAX_HOURS).as_secs() > 0);
+    }
+
+    // Fraud Detection
+
+    #[test]
+    fn test_withdrawal_emptying_account_right_after_deposit_raises_alert() {
+        let mut ta = TestApp::new();
+        let rule = Box::new(RapidWithdrawal::new(false));
+        let mut dp = Dispatcher::new(&mut ta.db).with_rule(rule);
+        dp.dispatch(&record("deposit", 10, 100, 200));
+        dp.dispatch(&record("withdrawal", 10, 200, 100));
+        dp.dispatch(&record("deposit", 10, 101, 100));
+        dp.dispatch(&record("deposit", 20, 102, 100));
+        dp.dispatch(&record("withdrawal", 10, 201, 200));
+        assert_eq!(dp.alerts().len(), 1);
+        assert_eq!((dp.alerts()[0].client, dp.alerts()[0].tx), (10, 201));
+        assert!(!dp.alerts()[0].locked);
+        assert!(!ta.db.accounts()[&10].locked);
+    }
+
+    #[test]
+    fn test_repeated_disputes_raise_alert_and_lock_account() {
+        let mut ta = TestApp::new();
+        let rule = Box::new(RepeatedDisputes::new(2, true));
+        let mut dp = Dispatcher::new(&mut ta.db).with_rule(rule);
+        dp.dispatch(&record("deposit", 10, 100, 200));
+        dp.dispatch(&record("dispute", 10, 100, 10));
+        dp.dispatch(&record("dispute", 10, 100, 10));
+        assert!(dp.alerts().is_empty());
+        dp.dispatch(&record("dispute", 10, 100, 10));
+        assert_eq!(dp.alerts().len(), 1);
+        assert!(dp.alerts()[0].locked);
+        assert!(ta.first_account().locked);
+        ta.assert_first_account_held(30);
+    }
+
+    #[test]
+    fn test_burst_of_new_accounts_raises_alert() {
+        let mut ta = TestApp::new();
+        let rule = Box::new(AccountBurst::new(2, 4, false));
+        let mut dp = Dispatcher::new(&mut ta.db).with_rule(rule);
+        dp.dispatch(&record("deposit", 10, 100, 10));
+        dp.dispatch(&record("deposit", 20, 101, 10));
+        dp.dispatch(&record("deposit", 20, 102, 10));
+        dp.dispatch(&record("deposit", 20, 103, 10));
+        dp.dispatch(&record("deposit", 30, 104, 10));
+        dp.dispatch(&record("deposit", 40, 105, 10));
+        assert!(dp.alerts().is_empty());
+        dp.dispatch(&record("deposit", 50, 106, 10));
+        assert_eq!(dp.alerts().len(), 1);
+        assert_eq!(dp.alerts()[0].client, 50);
+    }
+
+    // Account Closure
+
+    #[test]
+    fn test_close_pays_out_available_funds() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("withdrawal", 10, 200, 50);
+        ta.dispatch("close", 10, 300, None);
+        ta.assert_first_account_total(0);
+        assert!(ta.first_account().closed);
+        assert!(!ta.first_account().locked);
+    }
+
+    #[test]
+    fn test_closed_column_is_exported_on_request() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("close", 10, 300, None);
+        let mut output = Vec::new();
+        CsvExporter::new(csv::Writer::from_writer(&mut output))
+            .with_closed(true)
+            .dump_accounts(ta.db.accounts())
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("client,available,held,total,locked,closed\n"));
+        assert!(output.ends_with(",false,true\n"));
+    }
+
+    #[test]
+    fn test_close_is_denied_with_held_funds() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("authorize", 10, 300, 50);
+        ta.dispatch("close", 10, 301, None);
+        assert!(!ta.first_account().closed);
+        ta.dispatch("void", 10, 300, None);
+        ta.dispatch("dispute", 10, 100, None);
+        ta.dispatch("close", 10, 301, None);
+        assert!(!ta.first_account().closed);
+        ta.dispatch("resolve", 10, 100, None);
+        ta.dispatch("close", 10, 301, None);
+        assert!(ta.first_account().closed);
+    }
+
+    #[test]
+    fn test_transactions_on_closed_account_are_denied() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("close", 10, 300, None);
+        ta.dispatch("deposit", 10, 101, 100);
+        ta.dispatch("dispute", 10, 100, None);
+        ta.dispatch("dispute", 10, 300, None);
+        ta.assert_first_account_total(0);
+        ta.assert_first_account_held(0);
+    }
+
+    #[test]
+    fn test_late_chargeback_on_closed_account_is_applied() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("deposit", 10, 101, 100);
+        ta.dispatch("close", 10, 300, None);
+        ta.dispatch("chargeback", 10, 100, 150);
+        ta.dispatch("chargeback", 10, 101, None);
+        ta.dispatch("chargeback", 10, 100, None);
+        ta.dispatch("chargeback", 10, 300, None);
+        ta.assert_first_account_total(-250);
+        ta.assert_first_account_held(0);
+        assert!(ta.first_account().locked);
+    }
+
+    // Reversal
+
+    #[test]
+    fn test_reversal_undoes_deposit_and_withdrawal() {
+        let mut ta = TestApp::new();
+        ta.config.record_statements = true;
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("withdrawal", 10, 101, 50);
+        ta.dispatch("reversal", 10, 101, None);
+        ta.assert_first_account_total(200);
+        ta.dispatch("reversal", 10, 100, None);
+        ta.assert_first_account_total(0);
+        let statement = ta.first_account().statement();
+        assert_eq!(statement[3].r#type, "reversal");
+        assert_eq!(statement[3].amount, Decimal::from(-200));
+        assert_eq!(statement[3].status, Some(TransferState::Reversed));
+    }
+
+    #[test]
+    fn test_reversed_transfer_cannot_be_disputed_nor_reversed_again() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("reversal", 10, 100, None);
+        let mut dispatcher = Dispatcher::new(&mut ta.db);
+        let outcome = dispatcher.try_dispatch(&record("dispute", 10, 100, None));
+        assert_eq!(
+            outcome,
+            Err(TransactionError::deny("Corresponding transfer reversed"))
+        );
+        let outcome = dispatcher.try_dispatch(&record("reversal", 10, 100, None));
+        assert!(matches!(outcome, Err(TransactionError::Denied(_))));
+        ta.assert_first_account_total(0);
+    }
+
+    #[test]
+    fn test_reversal_requires_available_funds_unless_allowed() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("withdrawal", 10, 101, 150);
+        ta.dispatch("reversal", 10, 100, None);
+        ta.assert_first_account_total(50);
+        ta.config.reversal_overdraft = true;
+        ta.dispatch("reversal", 10, 100, None);
+        ta.assert_first_account_total(-150);
+    }
+
+    #[test]
+    fn test_disputed_transfer_cannot_be_reversed() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, None);
+        ta.dispatch("reversal", 10, 100, None);
+        ta.assert_first_account_total(200);
+        ta.assert_first_account_held(200);
+    }
+
+    // Manual Adjustments
+
+    fn adjustment(
+        transaction_type: &str,
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        amount: i32,
+        reason: Option<&str>,
+    ) -> Result<Record, ImportError> {
+        let mut row = record(transaction_type, client_id, transaction_id, amount);
+        if let Ok(rec) = &mut row {
+            rec.reason = reason.map(str::to_string);
+            rec.note = Some("ledger fix".to_string());
+        }
+        row
+    }
+
+    #[test]
+    fn test_adjustments_change_total_and_are_reported() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        let mut dispatcher = Dispatcher::new(&mut ta.db);
+        dispatcher.dispatch(&adjustment("adjust_credit", 10, 101, 30, Some("FEE")));
+        dispatcher.dispatch(&adjustment("adjust_debit", 10, 102, 50, Some("DUP")));
+        let reported: Vec<_> = dispatcher
+            .adjustments()
+            .iter()
+            .map(|entry| (entry.tx, entry.amount, entry.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                (101, Decimal::from(30), "FEE"),
+                (102, Decimal::from(-50), "DUP")
+            ]
+        );
+        assert_eq!(dispatcher.statistics().adjusted, Decimal::from(-20));
+        ta.assert_first_account_total(180);
+    }
+
+    #[test]
+    fn test_adjustment_without_reason_code_is_rejected() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        let row = adjustment("adjust_credit", 10, 101, 30, None);
+        let outcome = Dispatcher::new(&mut ta.db).try_dispatch(&row);
+        assert!(matches!(outcome, Err(TransactionError::Rejected(_))));
+        ta.assert_first_account_total(200);
+    }
+
+    #[test]
+    fn test_adjustment_cannot_be_disputed_nor_reversed() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        let row = adjustment("adjust_credit", 10, 101, 30, Some("FEE"));
+        Dispatcher::new(&mut ta.db).dispatch(&row);
+        ta.dispatch("dispute", 10, 101, None);
+        ta.dispatch("reversal", 10, 101, None);
+        ta.assert_first_account_total(230);
+        ta.assert_first_account_held(0);
+    }
+
+    #[test]
+    fn test_adjustment_of_frozen_account_requires_policy() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 50);
+        ta.dispatch("chargeback", 10, 100, None);
+        let row = adjustment("adjust_credit", 10, 101, 30, Some("FEE"));
+        Dispatcher::new(&mut ta.db).dispatch(&row);
+        ta.assert_first_account_total(150);
+        ta.config.adjustments_on_frozen = true;
+        let config = ta.config.clone();
+        Dispatcher::new(&mut ta.db)
+            .with_config(config)
+            .dispatch(&row);
+        ta.assert_first_account_total(180);
+    }
+
+    // Account Opening
+
+    #[test]
+    fn test_open_creates_empty_account_with_metadata() {
+        let mut ta = TestApp::new();
+        let mut row = record("open", 10, 1, 500);
+        if let Ok(rec) = &mut row {
+            rec.tier = Some("gold".to_string());
+            rec.currency = Some("EUR".to_string());
+        }
+        Dispatcher::new(&mut ta.db).dispatch(&row);
+        ta.assert_first_account_total(0);
+        assert_eq!(ta.first_account().tier.as_deref(), Some("gold"));
+        assert_eq!(ta.first_account().currency.as_deref(), Some("EUR"));
+        assert_eq!(ta.first_account().credit_limit, Decimal::from(500));
+    }
+
+    #[test]
+    fn test_open_of_existing_account_is_rejected() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        let outcome = Dispatcher::new(&mut ta.db).try_dispatch(&record("open", 10, 1, 500));
+        assert!(matches!(outcome, Err(TransactionError::Rejected(_))));
+        assert_eq!(ta.first_account().credit_limit, Decimal::from(0));
+    }
+
+    #[test]
+    fn test_deposit_to_account_not_opened_is_rejected_when_opening_required() {
+        let mut ta = TestApp::new();
+        ta.config.require_open = true;
+        ta.dispatch("deposit", 10, 100, 200);
+        assert_eq!(ta.db.accounts().len(), 0);
+        ta.dispatch("open", 10, 1, None);
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.assert_first_account_total(200);
+    }
+
+    // Statements
+
+    #[test]
+    fn test_statement_lists_applied_transactions_with_running_balances() {
+        let mut ta = TestApp::new();
+        ta.config.record_statements = true;
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("withdrawal", 10, 101, 50);
+        ta.dispatch("withdrawal", 10, 102, 500);
+        ta.dispatch("dispute", 10, 100, 100);
+        ta.dispatch("chargeback", 10, 100, None);
+        ta.dispatch("deposit", 20, 200, 10);
+        let statement = ta.db.statements(Some(10));
+        let lines: Vec<_> = statement
+            .iter()
+            .map(|entry| {
+                (
+                    entry.r#type.as_str(),
+                    entry.tx,
+                    entry.amount,
+                    entry.held,
+                    entry.total,
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (
+                    "deposit",
+                    100,
+                    Decimal::from(200),
+                    Decimal::from(0),
+                    Decimal::from(200)
+                ),
+                (
+                    "withdrawal",
+                    101,
+                    Decimal::from(-50),
+                    Decimal::from(0),
+                    Decimal::from(150)
+                ),
+                (
+                    "dispute",
+                    100,
+                    Decimal::from(0),
+                    Decimal::from(100),
+                    Decimal::from(150)
+                ),
+                (
+                    "chargeback",
+                    100,
+                    Decimal::from(-100),
+                    Decimal::from(0),
+                    Decimal::from(50)
+                ),
+            ]
+        );
+        assert_eq!(statement[2].status, Some(TransferState::Disputed));
+        assert_eq!(statement[3].status, Some(TransferState::ChargedBack));
+        assert_eq!(ta.db.statements(None).len(), 5);
+    }
+
+    #[test]
+    fn test_statements_are_not_recorded_unless_requested() {
+        let mut ta = TestApp::new();
+        ta.config.dispute_deadline = Some(config::days(30));
+        ta.dispatch_on_day(1, "deposit", 10, 100, 200);
+        ta.dispatch_on_day(2, "dispute", 10, 100, None);
+        ta.dispatch_on_day(40, "withdrawal", 10, 101, 50);
+        ta.assert_first_account_total(150);
+        ta.assert_first_account_held(0);
+        let report = operations::accrue_interest(
+            &mut ta.db,
+            &interest_policy(),
+            &mut Statistics::default(),
+            false,
+        );
+        assert_eq!(report.payments.len(), 1);
+        assert!(ta.db.statements(None).is_empty());
+    }
+
+    #[test]
+    fn test_withdrawal_is_recorded_but_cannot_be_disputed() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("withdrawal", 10, 101, 50);
+        let outcome = Dispatcher::new(&mut ta.db).try_dispatch(&record("dispute", 10, 101, None));
+        assert!(matches!(outcome, Err(TransactionError::Rejected(_))));
+        let outcome = Dispatcher::new(&mut ta.db).try_dispatch(&record("withdrawal", 10, 101, 50));
+        assert!(matches!(outcome, Err(TransactionError::Rejected(_))));
+        ta.assert_first_account_total(150);
+    }
+
+    // Interest
+
+    fn interest_policy() -> InterestPolicy {
+        InterestPolicy {
+            annual_rate: Decimal::new(2, 2),
+            tier_rates: [("gold".to_string(), Decimal::new(5, 2))].into(),
+            days: 30,
+            transaction_id: 999,
+        }
+    }
+
+    #[test]
+    fn test_interest_is_credited_by_tier_rate_on_available_funds() {
+        let mut ta = TestApp::new();
+        let mut row = record("open", 10, 1, None);
+        if let Ok(rec) = &mut row {
+            rec.tier = Some("gold".to_string());
+        }
+        Dispatcher::new(&mut ta.db).dispatch(&row);
+        ta.dispatch("deposit", 10, 100, 1000);
+        ta.dispatch("deposit", 20, 200, 1000);
+        ta.dispatch("dispute", 20, 200, 500);
+        let report = operations::accrue_interest(
+            &mut ta.db,
+            &interest_policy(),
+            &mut Statistics::default(),
+            false,
+        );
+        let payments = &report.payments;
+        assert_eq!(payments.len(), 2);
+        // 1000 * 0.05 * 30 / 365 = 4.10958..., 500 * 0.02 * 30 / 365 = 0.82191...
+        assert_eq!((payments[0].client, payments[1].client), (10, 20));
+        assert_eq!(payments[0].amount, Decimal::new(41095, 4));
+        assert_eq!(payments[1].amount, Decimal::new(8219, 4));
+        assert_eq!(report.total, Decimal::new(49314, 4));
+        assert_eq!(
+            ta.db.accounts()[&10].amount_total,
+            Decimal::new(10041095, 4)
+        );
+        assert_eq!(
+            ta.db.accounts()[&20].amount_total,
+            Decimal::new(10008219, 4)
+        );
+    }
+
+    #[test]
+    fn test_interest_is_not_credited_to_closed_or_overdrawn_accounts() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 1000);
+        ta.dispatch("close", 10, 101, None);
+        ta.dispatch("deposit", 20, 200, 100);
+        ta.dispatch("set_limit", 20, 201, 500);
+        ta.dispatch("withdrawal", 20, 202, 300);
+        let report = operations::accrue_interest(
+            &mut ta.db,
+            &interest_policy(),
+            &mut Statistics::default(),
+            false,
+        );
+        assert!(report.payments.is_empty());
+    }
+
+    #[test]
+    fn test_interest_is_accrued_under_free_transaction_ids() {
+        let mut ta = TestApp::new();
+        ta.config.record_statements = true;
+        ta.dispatch("deposit", 10, 999, 1000);
+        ta.dispatch("deposit", 20, 200, 1000);
+        let first = operations::accrue_interest(
+            &mut ta.db,
+            &interest_policy(),
+            &mut Statistics::default(),
+            true,
+        );
+        let second = operations::accrue_interest(
+            &mut ta.db,
+            &interest_policy(),
+            &mut Statistics::default(),
+            true,
+        );
+        let ids: Vec<_> = first
+            .payments
+            .iter()
+            .chain(&second.payments)
+            .map(|p| p.tx)
+            .collect();
+        assert_eq!(ids, [998, 999, 997, 998]);
+        assert_eq!(ta.db.accounts()[&20].statement().len(), 3);
+    }
+
+    #[test]
+    fn test_interest_cannot_be_disputed() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 1000);
+        operations::accrue_interest(
+            &mut ta.db,
+            &interest_policy(),
+            &mut Statistics::default(),
+            false,
+        );
+        let outcome = Dispatcher::new(&mut ta.db).try_dispatch(&record("dispute", 10, 999, None));
+        assert!(matches!(outcome, Err(TransactionError::Rejected(_))));
+        ta.assert_first_account_held(0);
+    }
+
+    // Reconciliation
+
+    fn expected_account(client: ClientId, total: Decimal, locked: bool) -> ExpectedAccount {
+        ExpectedAccount {
+            client,
+            available: total,
+            held: Decimal::ZERO,
+            total,
+            locked,
+        }
+    }
+
+    #[test]
+    fn test_reconciliation_tolerates_differences_below_scale() {
+        let mut ta = TestApp::new();
+        ta.db.get_account_or_create(10).amount_total = Decimal::new(1001234, 4);
+        let expected = [expected_account(10, Decimal::new(10012, 2), false)];
+        assert!(operations::reconcile(&ta.db, &expected, 2).is_empty());
+        let report = operations::reconcile(&ta.db, &expected, 4);
+        let fields: Vec<_> = report.iter().map(|diff| diff.field).collect();
+        assert_eq!(fields, ["available", "total"]);
+    }
+
+    #[test]
+    fn test_reconciliation_reports_missing_accounts_and_locks() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("deposit", 30, 300, 100);
+        let expected = [
+            expected_account(10, Decimal::from(200), true),
+            expected_account(20, Decimal::from(100), false),
+        ];
+        let report = operations::reconcile(&ta.db, &expected, 4);
+        let diffs: Vec<_> = report
+            .iter()
+            .map(|diff| (diff.client, diff.field, diff.actual.as_str()))
+            .collect();
+        assert_eq!(
+            diffs,
+            [
+                (10, "locked", "false"),
+                (20, "account", "missing"),
+                (30, "account", "present")
+            ]
+        );
+    }
+
+    // Summary
+
+    #[test]
+    fn test_summary_totals_all_accounts() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 50);
+        ta.dispatch("deposit", 20, 200, 100);
+        ta.dispatch("close", 20, 201, None);
+        let summary = operations::summarize(&ta.db);
+        assert_eq!(
+            (summary.accounts, summary.locked, summary.closed),
+            (2, 0, 1)
+        );
+        assert_eq!(summary.available, Decimal::from(150));
+        assert_eq!(summary.held, Decimal::from(50));
+        assert_eq!(summary.total, Decimal::from(200));
+    }
+
+    // Outcome Statistics
+
+    #[test]
+    fn test_outcomes_are_counted_by_reason() {
+        let mut ta = TestApp::new();
+        let mut dp = Dispatcher::new(&mut ta.db);
+        dp.dispatch(&record("deposit", 10, 100, 200));
+        dp.dispatch(&record("withdrawal", 10, 101, 500));
+        dp.dispatch(&record("withdrawal", 10, 102, 500));
+        dp.dispatch(&record("deposit", 10, 103, None));
+        dp.dispatch(&record("deposit", 10, 104, None));
+        let statistics = dp.statistics();
+        let outcomes: Vec<_> = statistics
+            .outcomes()
+            .map(|outcome| (outcome.outcome, outcome.reason.as_str(), outcome.count))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                (OutcomeKind::Completed, "", 1),
+                (OutcomeKind::Denied, "Available funds are not sufficient", 2),
+                (OutcomeKind::Rejected, "Amount missing", 2),
+            ]
+        );
+        assert_eq!(statistics.outcomes().nth(2).unwrap().first_record, 4);
+        assert_eq!(statistics.records, 5);
+    }
+
+    #[test]
+    fn test_statistics_count_accounts_and_funds() {
+        let mut ta = TestApp::new();
+        let mut dp = Dispatcher::new(&mut ta.db);
+        dp.dispatch(&record("deposit", 10, 100, 200));
+        dp.dispatch(&record("deposit", 20, 200, 50));
+        dp.dispatch(&record("withdrawal", 10, 101, 30));
+        dp.dispatch(&record("dispute", 20, 200, None));
+        dp.dispatch(&record("chargeback", 20, 200, None));
+        dp.dispatch(&record("withdrawal", 20, 201, 10));
+        let statistics = dp.statistics();
+        assert_eq!(statistics.types["deposit"], 2);
+        assert_eq!((statistics.completed, statistics.denied), (5, 1));
+        assert_eq!(statistics.accounts_created, 2);
+        assert_eq!(statistics.accounts_locked, 1);
+        assert_eq!(statistics.deposited, Decimal::from(250));
+        assert_eq!(statistics.withdrawn, Decimal::from(30));
+        assert_eq!(statistics.charged_back, Decimal::from(50));
+    }
+
+    // Metrics
+
+    #[test]
+    fn test_metrics_track_transactions_and_account_gauges() {
+        let mut ta = TestApp::new();
+        let metrics = SharedMetrics::default();
+        let mut dp = Dispatcher::new(&mut ta.db).with_metrics(metrics.clone());
+        dp.dispatch(&record("deposit", 10, 100, 200));
+        dp.dispatch(&record("deposit", 20, 200, 100));
+        dp.dispatch(&record("dispute", 10, 100, 50));
+        dp.dispatch(&record("dispute", 20, 200, None));
+        dp.dispatch(&record("chargeback", 20, 200, None));
+        dp.dispatch(&record("withdrawal", 20, 201, 10));
+        dp.dispatch(&record("de\"posit", 20, 202, 10));
+        dp.dispatch(&record("depos1t", 20, 203, 10));
+        let text = metrics.lock().unwrap().to_string();
+        assert!(
+            text.contains("banking_transactions_total{type=\"deposit\",outcome=\"completed\"} 2\n")
+        );
+        assert!(
+            text.contains("banking_transactions_total{type=\"withdrawal\",outcome=\"denied\"} 1\n")
+        );
+        assert!(text.contains("{type=\"unknown\",outcome=\"rejected\"} 2\n"));
+        assert!(!text.contains("de\\\"posit") && !text.contains("depos1t"));
+        assert!(text.contains("banking_processing_seconds_count 8\n"));
+        assert!(text.contains("banking_accounts 2\n"));
+        assert!(text.contains("banking_held_funds 50.0000\n"));
+        assert!(text.contains("banking_locked_accounts 1\n"));
+    }
+
+    // Structured Logging
+
+    #[test]
+    fn test_importer_attaches_input_line_to_records() {
+        let input = "type,client,tx,amount\ndeposit,1,1,10\nwithdrawal,1,2,5\n";
+        let rows: Vec<_> = CsvImporter::new(input.as_bytes()).into_rows().collect();
+        let lines: Vec<_> = rows
+            .iter()
+            .map(|row| row.as_ref().unwrap().position.as_ref().unwrap().line)
+            .collect();
+        assert_eq!(lines, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_importer_stops_after_malformed_header() {
+        let input: &[u8] = b"type,cli\xffent,tx,amount\ndeposit,1,1,10\n";
+        let rows: Vec<_> = CsvImporter::new(input).into_rows().collect();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].is_err());
+    }
+
+    #[test]
+    fn test_importer_reads_quoted_note_with_comma() {
+        let input = "type,client,tx,amount,reason,note\n\
+            adjust_credit,1,1,10,fix,\"duplicate, see ticket\"\n";
+        let rows: Vec<_> = CsvImporter::new(input.as_bytes()).into_rows().collect();
+        let rec = rows[0].as_ref().unwrap();
+        assert_eq!(rec.reason.as_deref(), Some("fix"));
+        assert_eq!(rec.note.as_deref(), Some("duplicate, see ticket"));
+    }
+
+    #[test]
+    fn test_json_log_line_carries_context_fields() {
+        let fields: &[(&str, log::kv::Value)] = &[
+            ("client", 7u16.into()),
+            ("tx", 42u32.into()),
+            ("type", "deposit".into()),
+            ("line", log::kv::Value::null()),
+        ];
+        let line = logging::format_json(
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .args(format_args!("Tranaction denied"))
+                .key_values(&fields)
+                .build(),
+        );
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["level"], "WARN");
+        assert_eq!(json["message"], "Tranaction denied");
+        assert_eq!(json["client"], 7);
+        assert_eq!(json["tx"], 42);
+        assert_eq!(json["type"], "deposit");
+        assert!(json["line"].is_null());
+    }
+
+    // Source Positions
+
+    #[test]
+    fn test_importer_attaches_byte_offset_to_records() {
+        let input = "type,client,tx,amount\ndeposit,1,1,10\nwithdrawal,1,2,5\n";
+        let rows: Vec<_> = CsvImporter::new(input.as_bytes()).into_rows().collect();
+        let position = rows[1].as_ref().unwrap().position.clone().unwrap();
+        assert_eq!(position.file, None);
+        assert_eq!((position.line, position.byte), (3, 37));
+        assert_eq!(position.to_string(), "line 3 (byte 37)");
+    }
+
+    #[test]
+    fn test_malformed_record_carries_file_of_input() {
+        let path = std::path::Path::new("examples/erroneous_transactions.csv");
+        let importer = CsvImporter::from_path(path).unwrap();
+        let row = importer.into_rows().next().unwrap();
+        let mut db = MemDatabase::new();
+        let err = Dispatcher::new(&mut db).try_dispatch(&row).unwrap_err();
+        assert_eq!(err.reason(), "Malformed record");
+        let position = err.position().unwrap();
+        assert_eq!(
+            position.to_string(),
+            "examples/erroneous_transactions.csv:2 (byte 25)"
+        );
+    }
+
+    #[test]
+    fn test_error_carries_position_of_record() {
+        let mut db = MemDatabase::new();
+        let mut dispatcher = Dispatcher::new(&mut db);
+        let position = SourcePosition {
+            file: Some("transactions.csv".into()),
+            line: 7,
+            byte: 120,
+        };
+        let mut row = record("withdrawal", 1, 1, 10);
+        row.as_mut().unwrap().position = Some(position.clone());
+        let outcome = dispatcher.try_dispatch(&row);
+        let err = outcome.unwrap_err();
+        assert_eq!(err.position(), Some(&position));
+        assert_eq!(err.cause(), "Account not found");
+    }
+
+    #[test]
+    fn test_statistics_report_position_of_first_failure() {
+        let mut db = MemDatabase::new();
+        let mut dispatcher = Dispatcher::new(&mut db);
+        for (line, tx) in [(2, 1), (3, 2)] {
+            let mut row = record("withdrawal", 1, tx, 10);
+            let position = SourcePosition {
+                file: Some("transactions.csv".into()),
+                line,
+                byte: 0,
+            };
+            row.as_mut().unwrap().position = Some(position);
+            dispatcher.dispatch(&row);
+        }
+        let outcome = dispatcher.statistics().outcomes().next().unwrap();
+        assert_eq!(outcome.count, 2);
+        let position = outcome.first_position.as_ref().unwrap();
+        assert_eq!(position.to_string(), "transactions.csv:2 (byte 0)");
+    }
+
+    // Strict Mode
+
+    #[test]
+    fn test_last_rejection_ignores_denials() {
+        let mut db = MemDatabase::new();
+        let mut dispatcher = Dispatcher::new(&mut db);
+        dispatcher.dispatch(&record("bogus", 1, 1, 10));
+        dispatcher.dispatch(&record("deposit", 1, 2, 10));
+        dispatcher.dispatch(&record("withdrawal", 1, 3, 100));
+        let last = dispatcher.last_rejection().unwrap();
+        assert_eq!(last.cause(), "Invalid transaction type: \"bogus\"");
+        assert_eq!(last.reason(), "Invalid transaction type");
+        assert_eq!(dispatcher.statistics().rejected, 1);
+    }
+
+    #[test]
+    fn test_aborted_batch_reports_last_rejection() {
+        let position = SourcePosition {
+            file: Some("transactions.csv".into()),
+            line: 4,
+            byte: 49,
+        };
+        let aborted = BatchAborted {
+            rejected: 3,
+            last: TransactionError::reject("Amount missing").at(Some(position)),
+        };
+        assert_eq!(
+            aborted.to_string(),
+            "Processing aborted after 3 rejected records, last at transactions.csv:4 (byte 49): Amount missing"
+        );
+    }
+
+    /// Batch options parsed from the command line, with reports written to unique temporary files
+    fn batch_options_with_reports(name: &str, args: &[&str]) -> (BatchOptions, Vec<PathBuf>) {
+        let reports: Vec<_> = ["idempotency", "alerts", "adjustments", "expiry", "interest"]
+            .iter()
+            .map(|report| {
+                let file = format!(
+                    "banking_system_{}_{}_{}.csv",
+                    name,
+                    report,
+                    std::process::id()
+                );
+                std::env::temp_dir().join(file)
+            })
+            .collect();
+        let paths: Vec<_> = reports.iter().map(|path| path.to_str().unwrap()).collect();
+        let mut argv = vec![
+            "bank",
+            "--idempotency-store",
+            paths[0],
+            "--fraud-rules",
+            "rapid-withdrawal",
+            "--alerts",
+            paths[1],
+            "--adjustments-report",
+            paths[2],
+            "--expire-disputes-after-transactions",
+            "100",
+            "--expiry-report",
+            paths[3],
+            "--interest-rate",
+            "0.02",
+            "--interest-report",
+            paths[4],
+        ];
+        argv.extend_from_slice(args);
+        argv.push("in.csv");
+        let batch = Cli::try_parse_from(argv).unwrap().batch;
+        (batch, reports)
+    }
+
+    fn aborted(
+        result: Result<(MemDatabase, Statistics), Box<dyn std::error::Error>>,
+    ) -> BatchAborted {
+        match result {
+            Err(err) => *err.downcast::<BatchAborted>().unwrap(),
+            Ok(_) => panic!("expected processing to be aborted"),
+        }
+    }
+
+    #[test]
+    fn test_strict_batch_aborts_at_first_rejection_without_output() {
+        let (options, reports) = batch_options_with_reports("strict", &["--strict"]);
+        let rows = vec![
+            record("deposit", 1, 1, 100),
+            record("withdrawal", 1, 2, 500),
+            record("bogus", 1, 3, 10),
+            record("deposit", 1, 4, 100),
+        ];
+        let err = aborted(run_batch(&options, false, rows.into_iter(), None));
+        assert_eq!(err.rejected, 1);
+        assert_eq!(err.last.reason(), "Invalid transaction type");
+        assert!(reports.iter().all(|path| !path.exists()));
+    }
+
+    #[test]
+    fn test_max_errors_batch_aborts_after_limit_without_output() {
+        let (options, reports) = batch_options_with_reports("max_errors", &["--max-errors", "2"]);
+        let rows = |rejections: TransactionId| {
+            let rejected = (0..rejections).map(|tx| record("dispute", 1, 10 + tx, None));
+            let denied = record("withdrawal", 1, 2, 500);
+            let completed = record("deposit", 1, 3, 100);
+            std::iter::once(record("deposit", 1, 1, 100))
+                .chain(rejected)
+                .chain([denied, completed])
+        };
+        let result = run_batch(&options, false, rows(2), None);
+        assert_eq!(result.unwrap().1.rejected, 2);
+        assert!(reports.iter().all(|path| path.exists()));
+        for path in &reports {
+            std::fs::remove_file(path).unwrap();
+        }
+
+        let err = aborted(run_batch(&options, false, rows(3), None));
+        assert_eq!(err.rejected, 3);
+        assert_eq!(err.last.reason(), "Corresponding transfer not found");
+        assert!(reports.iter().all(|path| !path.exists()));
+    }
+
+    #[test]
+    fn test_denials_never_abort_strict_batch() {
+        let (options, reports) = batch_options_with_reports("denials", &["--strict"]);
+        let mut rows = vec![record("deposit", 1, 1, 100)];
+        rows.extend((2..7).map(|tx| record("withdrawal", 1, tx, 500)));
+        let (_, statistics) = run_batch(&options, false, rows.into_iter(), None).unwrap();
+        assert_eq!((statistics.denied, statistics.rejected), (5, 0));
+        for path in &reports {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_errors_are_checked_against_limit_after_each_record() {
+        let mut db = MemDatabase::new();
+        let mut dispatcher = Dispatcher::new(&mut db);
+        dispatcher.dispatch(&record("deposit", 1, 1, 100));
+        dispatcher.dispatch(&record("withdrawal", 1, 2, 500));
+        assert!(check_errors(&dispatcher, Some(0)).is_ok());
+        for tx in 3..5 {
+            dispatcher.dispatch(&record("bogus", 1, tx, 10));
+            assert!(check_errors(&dispatcher, Some(2)).is_ok());
+            assert!(check_errors(&dispatcher, None).is_ok());
+        }
+        dispatcher.dispatch(&record("bogus", 1, 5, 10));
+        let err = check_errors(&dispatcher, Some(2)).unwrap_err();
+        assert_eq!(err.rejected, 3);
+        assert!(check_errors(&dispatcher, Some(3)).is_ok());
+    }
+
+    // Batches
+
+    #[test]
+    fn test_rollback_restores_changed_and_removes_created_accounts() {
+        let mut db = MemDatabase::new();
+        db.get_account_or_create(1).amount_total = Decimal::from(10);
+        db.begin();
+        db.get_account(1).unwrap().amount_total = Decimal::from(3);
+        db.get_account_or_create(2).amount_total = Decimal::from(5);
+        db.rollback();
+        assert_eq!(db.accounts()[&1].amount_total, Decimal::from(10));
+        assert!(!db.accounts().contains_key(&2));
+    }
+
+    #[test]
+    fn test_committed_batch_is_applied() {
+        let mut db = MemDatabase::new();
+        let mut dispatcher = Dispatcher::new(&mut db);
+        dispatcher.dispatch(&record("deposit", 1, 1, 100));
+        dispatcher.dispatch(&record("batch_begin", 0, 10, None));
+        dispatcher.dispatch(&record("deposit", 2, 11, 50));
+        dispatcher.dispatch(&record("withdrawal", 1, 12, 30));
+        let outcome = dispatcher.try_dispatch(&record("batch_commit", 0, 10, None));
+        assert_eq!(outcome, Ok(()));
+        assert_eq!(dispatcher.statistics().rolled_back, 0);
+        assert_eq!(db.accounts()[&1].amount_total, Decimal::from(70));
+        assert_eq!(db.accounts()[&2].amount_total, Decimal::from(50));
+    }
+
+    #[test]
+    fn test_failed_batch_is_rolled_back_as_a_whole() {
+        let mut db = MemDatabase::new();
+        let mut dispatcher = Dispatcher::new(&mut db);
+        dispatcher.dispatch(&record("deposit", 1, 1, 100));
+        dispatcher.dispatch(&record("batch_begin", 0, 10, None));
+        dispatcher.dispatch(&record("deposit", 2, 11, 50));
+        dispatcher.dispatch(&record("withdrawal", 1, 12, 500));
+        dispatcher.dispatch(&record("deposit", 1, 13, 5));
+        dispatcher.dispatch(&record("batch_commit", 0, 10, None));
+
+        let statistics = dispatcher.statistics();
+        assert_eq!(statistics.records, 6);
+        assert_eq!(statistics.rolled_back, 4);
+        assert_eq!((statistics.completed, statistics.denied), (1, 1));
+        let outcome = statistics.outcomes().last().unwrap();
+        assert_eq!(
+            outcome.reason,
+            "Batch rolled back: Available funds are not sufficient"
+        );
+        assert_eq!(statistics.deposited, Decimal::from(100));
+        assert_eq!(db.accounts()[&1].amount_total, Decimal::from(100));
+        assert!(!db.accounts().contains_key(&2));
+    }
+
+    #[test]
+    fn test_batch_left_open_is_rolled_back_at_the_end() {
+        let mut db = MemDatabase::new();
+        let mut dispatcher = Dispatcher::new(&mut db);
+        dispatcher.dispatch(&record("batch_begin", 0, 10, None));
+        dispatcher.dispatch(&record("deposit", 1, 11, 50));
+        dispatcher.finish();
+        let statistics = dispatcher.statistics();
+        assert_eq!((statistics.records, statistics.rolled_back), (2, 1));
+        assert_eq!(statistics.rejected, 1);
+        assert!(db.accounts().is_empty());
+    }
+
+    #[test]
+    fn test_batch_opened_by_try_dispatch_is_rolled_back_at_the_end() {
+        let mut db = MemDatabase::new();
+        let mut dispatcher = Dispatcher::new(&mut db);
+        dispatcher
+            .try_dispatch(&record("batch_begin", 0, 10, None))
+            .unwrap();
+        dispatcher.dispatch(&record("deposit", 1, 11, 50));
+        dispatcher.finish();
+        let statistics = dispatcher.statistics();
+        assert_eq!((statistics.records, statistics.rolled_back), (1, 1));
+        assert!(dispatcher.last_rejection().is_some());
+        assert!(db.accounts().is_empty());
+    }
+
+    #[test]
+    fn test_rolled_back_batch_is_forgotten_by_fraud_rules() {
+        let mut db = MemDatabase::new();
+        let rule = Box::new(RepeatedDisputes::new(1, true));
+        let mut dispatcher = Dispatcher::new(&mut db).with_rule(rule);
+        dispatcher.dispatch(&record("deposit", 1, 1, 100));
+        dispatcher.dispatch(&record("batch_begin", 0, 10, None));
+        dispatcher.dispatch(&record("dispute", 1, 1, 10));
+        dispatcher.dispatch(&record("dispute", 1, 1, 10));
+        assert_eq!(dispatcher.alerts().len(), 1);
+        dispatcher.dispatch(&record("bogus", 1, 12, None));
+        dispatcher.dispatch(&record("batch_commit", 0, 10, None));
+        assert!(dispatcher.alerts().is_empty());
+        dispatcher.dispatch(&record("dispute", 1, 1, 10));
+        assert!(dispatcher.alerts().is_empty());
+        dispatcher.dispatch(&record("dispute", 1, 1, 10));
+        assert_eq!(dispatcher.alerts().len(), 1);
+        assert!(db.accounts()[&1].locked);
+    }
+
+    #[test]
+    fn test_rolled_back_batch_is_not_remembered_as_processed() {
+        let mut db = MemDatabase::new();
+        let store = IdempotencyStore::new(10);
+        let mut dispatcher = Dispatcher::new(&mut db).with_idempotency(store);
+        dispatcher.dispatch(&record("batch_begin", 0, 10, None));
+        dispatcher.dispatch(&record("deposit", 1, 11, 50));
+        dispatcher.dispatch(&record("bogus", 1, 12, None));
+        dispatcher.dispatch(&record("batch_commit", 0, 10, None));
+        let store = dispatcher.idempotency().unwrap();
+        assert!(store
+            .get(&("deposit".to_string(), 1, 11, Decimal::from_i32(50)))
+            .is_none());
+    }
+
+    // Transaction Registry
+
+    #[derive(Debug)]
+    struct Unlock;
+
+    impl Transaction for Unlock {
+        fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+            account.locked = false;
+            Ok(())
+        }
+
+        fn allowed_on_frozen_account(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_registered_type_is_dispatched() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("dispute", 10, 100, 50);
+        ta.dispatch("chargeback", 10, 100, None);
+        assert!(ta.first_account().locked);
+        let registry =
+            TransactionRegistry::default().with_type("unlock", |_, _| Ok(Box::new(Unlock)));
+        let mut dispatcher = Dispatcher::new(&mut ta.db).with_registry(registry);
+        dispatcher.dispatch(&record("unlock", 10, 1, None));
+        assert_eq!(dispatcher.statistics().completed, 1);
+        assert!(!ta.first_account().locked);
+    }
+
+    #[test]
+    fn test_aliases_and_case_are_resolved_to_canonical_type() {
+        let mut ta = TestApp::new();
+        let registry = TransactionRegistry::default()
+            .with_alias("withdraw", "withdrawal")
+            .case_insensitive();
+        ta.config.record_statements = true;
+        let mut dispatcher = Dispatcher::new(&mut ta.db)
+            .with_config(ta.config.clone())
+            .with_registry(registry);
+        dispatcher.dispatch(&record("DEPOSIT", 10, 100, 200));
+        dispatcher.dispatch(&record("Withdraw", 10, 101, 50));
+        let types: Vec<_> = dispatcher.statistics().types.keys().cloned().collect();
+        assert_eq!(types, ["deposit", "withdrawal"]);
+        ta.assert_first_account_total(150);
+        assert_eq!(ta.first_account().statement()[1].r#type, "withdrawal");
+    }
+
+    #[test]
+    fn test_types_are_case_sensitive_by_default() {
+        let registry = TransactionRegistry::default().with_alias("withdraw", "withdrawal");
+        assert_eq!(registry.resolve("withdraw"), Some("withdrawal"));
+        assert_eq!(registry.resolve("Deposit"), None);
+        let mut ta = TestApp::new();
+        let outcome = Dispatcher::new(&mut ta.db).try_dispatch(&record("Deposit", 10, 100, 200));
+        assert!(matches!(outcome, Err(TransactionError::Rejected(_))));
+    }
+
+    // Mixed
+
+    #[test]
+    fn test_deposit_on_frozen_account_possible() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("deposit", 10, 101, 100);
+        ta.dispatch("dispute", 10, 100, None);
+        ta.dispatch("chargeback", 10, 100, None);
+        ta.dispatch("deposit", 10, 102, 300);
+        ta.assert_first_account_total(400);
+        ta.assert_first_account_held(00);
+        assert_eq!(ta.first_account().locked, true);
+    }
+
+    #[test]
+    fn test_withdrawal_from_frozen_account_not_possible() {
+        let mut ta = TestApp::new();
+        ta.dispatch("deposit", 10, 100, 200);
+        ta.dispatch("deposit", 10, 101, 100);
+        ta.dispatch("dispute", 10, 100, None);
+        ta.dispatch("chargeback", 10, 100, None);
+        ta.dispatch("withdrawal", 10, 200, 50);
+        ta.assert_first_account_total(100);
+        ta.assert_first_account_held(00);
+        assert_eq!(ta.first_account().locked, true);
+    }
+
+    // Idempotency
+
+    #[test]
+    fn test_redelivered_withdrawal_is_not_applied_again() {
+        let mut ta = TestApp::new();
+        let mut dp = Dispatcher::new(&mut ta.db).with_idempotency(IdempotencyStore::new(10));
+        dp.dispatch(&record("deposit", 10, 100, 200));
+        dp.dispatch(&record("withdrawal", 10, 200, 50));
+        dp.dispatch(&record("withdrawal", 10, 200, 50));
+        ta.assert_first_account_total(150);
+    }
+
+    #[test]
+    fn test_redelivered_record_returns_original_outcome() {
+        let mut ta = TestApp::new();
+        let mut dp = Dispatcher::new(&mut ta.db).with_idempotency(IdempotencyStore::new(10));
+        dp.dispatch(&record("deposit", 10, 100, 20));
+        let original = dp.try_dispatch(&record("withdrawal", 10, 200, 50));
+        dp.dispatch(&record("deposit", 10, 101, 100));
+        let replayed = dp.try_dispatch(&record("withdrawal", 10, 200, 50));
+        assert!(matches!(original, Err(TransactionError::Denied(_))));
+        assert_eq!(original, replayed);
+        ta.assert_first_account_total(120);
+    }
+
+    #[test]
+    fn test_redelivery_is_not_detected_once_evicted_from_store() {
+        let mut ta = TestApp::new();
+        let mut dp = Dispatcher::new(&mut ta.db).with_idempotency(IdempotencyStore::new(1));
+        dp.dispatch(&record("deposit", 10, 100, 200));
+        dp.dispatch(&record("dispute", 10, 100, None));
+        dp.dispatch(&record("resolve", 10, 100, None));
+        dp.dispatch(&record("dispute", 10, 100, None));
+        ta.assert_first_account_held(200);
+    }
+
+    #[test]
+    fn test_partial_disputes_are_not_redeliveries() {
+        let mut ta = TestApp::new();
+        let mut dp = Dispatcher::new(&mut ta.db).with_idempotency(IdempotencyStore::new(10));
+        dp.dispatch(&record("deposit", 10, 100, 100));
+        dp.dispatch(&record("dispute", 10, 100, 10));
+        dp.dispatch(&record("dispute", 10, 100, 20));
+        dp.dispatch(&record("dispute", 10, 100, 20));
+        ta.assert_first_account_held(30);
+    }
+
+    #[test]
+    fn test_idempotency_store_survives_save_and_load() {
+        let name = format!("banking_system_idempotency_{}.csv", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let mut ta = TestApp::new();
+        {
+            let store = IdempotencyStore::new(10);
+            let mut dp = Dispatcher::new(&mut ta.db).with_idempotency(store);
+            dp.dispatch(&record("deposit", 10, 100, 200));
+            dp.dispatch(&record("withdrawal", 10, 200, 500));
+            dp.idempotency().unwrap().save(&path).unwrap();
+        }
+        let store = IdempotencyStore::load(&path, 10).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut dp = Dispatcher::new(&mut ta.db).with_idempotency(store);
+        dp.dispatch(&record("deposit", 10, 100, 200));
+        dp.dispatch(&record("deposit", 10, 101, 400));
+        let replayed = dp.try_dispatch(&record("withdrawal", 10, 200, 500));
+        assert!(matches!(replayed, Err(TransactionError::Denied(_))));
+        ta.assert_first_account_total(600);
+    }
+
+    // Command Line
+
+    #[test]
+    fn test_cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_bare_input_file_is_processed() {
+        let argv = ["bank", "--strict", "--statements", "st.csv", "in.csv"];
+        let command = Cli::try_parse_from(argv).unwrap().into_command();
+        let Some(Command::Process(args)) = command else {
+            panic!("expected process, got {:?}", command);
+        };
+        assert_eq!(args.transactions, std::path::Path::new("in.csv"));
+        assert!(args.batch.strict);
+        assert!(args.statements.statements.is_some());
+        assert!(!args.dry_run);
+        assert!(Cli::try_parse_from(["bank", "--strict"])
+            .unwrap()
+            .into_command()
+            .is_none());
+    }
+
+    #[test]
+    fn test_each_subcommand_parses() {
+        let parse = |argv: &[&str]| Cli::try_parse_from(argv).unwrap().into_command().unwrap();
+        let command = parse(&["bank", "process", "--show-limits", "--dry-run", "in.csv"]);
+        assert!(
+            matches!(command, Command::Process(args) if args.output.show_limits && args.dry_run)
+        );
+        let command = parse(&["bank", "validate", "--require-open", "in.csv"]);
+        assert!(matches!(command, Command::Validate(args) if args.rules.require_open));
+        let command = parse(&["bank", "replay", "--until", "10", "a.csv", "b.csv"]);
+        assert!(matches!(command, Command::Replay(args) if args.transactions.len() == 2));
+        let command = parse(&["bank", "query", "--client", "1", "--statement", "in.csv"]);
+        assert!(matches!(command, Command::Query(args) if args.statement && args.client == [1]));
+        let command = parse(&[
+            "bank",
+            "reconcile",
+            "--scale",
+            "2",
+            "in.csv",
+            "expected.csv",
+        ]);
+        assert!(matches!(command, Command::Reconcile(args) if args.scale == 2));
+        let command = parse(&["bank", "serve", "--metrics-address", "localhost:9000"]);
+        assert!(matches!(command, Command::Serve(args) if args.metrics_address.is_some()));
+        let command = parse(&["bank", "stats", "--stats-format", "json", "-l", "in.csv"]);
+        assert!(matches!(command, Command::Stats(args) if args.log.log));
+    }
+
+    #[test]
+    fn test_inapplicable_options_are_rejected() {
+        for argv in [
+            ["bank", "validate", "--statements", "st.csv", "in.csv"],
+            ["bank", "validate", "--alerts", "al.csv", "in.csv"],
+            ["bank", "validate", "--interest-rate", "0.02", "in.csv"],
+            [
+                "bank",
+                "validate",
+                "--expire-disputes-after-days",
+                "1",
+                "in.csv",
+            ],
+            ["bank", "query", "--statements", "st.csv", "in.csv"],
+            ["bank", "query", "--statement-client", "1", "in.csv"],
+            ["bank", "stats", "--statements", "st.csv", "in.csv"],
+            [
+                "bank",
+                "reconcile",
+                "--show-limits",
+                "in.csv",
+                "expected.csv",
+            ],
+        ] {
+            assert!(Cli::try_parse_from(argv).is_err(), "{:?}", argv);
+        }
+        assert!(Cli::try_parse_from(["bank", "validate", "--strict", "in.csv"]).is_err());
+        assert!(Cli::try_parse_from(["bank", "stats", "--show-closed", "in.csv"]).is_err());
     }
 }
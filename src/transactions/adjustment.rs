@@ -0,0 +1,68 @@
+use crate::database::{Account, Transfer, TransferKind};
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::{Amount, ClientId, Timestamp, TransactionId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdjustmentKind {
+    Credit,
+    Debit,
+}
+
+/// Correction of the balance by an operator, which cannot be disputed
+#[derive(Debug, derive_new::new)]
+pub struct Adjustment {
+    transaction_id: TransactionId,
+    kind: AdjustmentKind,
+    amount: Amount,
+    timestamp: Option<Timestamp>,
+    allowed_on_frozen_account: bool,
+}
+
+impl Adjustment {
+    /// Change of total funds, negative for debits
+    fn change(&self) -> Amount {
+        match self.kind {
+            AdjustmentKind::Credit => self.amount,
+            AdjustmentKind::Debit => -self.amount,
+        }
+    }
+}
+
+impl Transaction for Adjustment {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        if account.contains_transfer(&self.transaction_id) {
+            Err(TransactionError::reject("Duplicated transaction ID"))?;
+        }
+        if self.kind == AdjustmentKind::Debit && account.amount_available() < self.amount {
+            Err(TransactionError::deny("Available funds are not sufficient"))?;
+        }
+        account.amount_total += self.change();
+
+        let transfer = Transfer::new(TransferKind::Adjustment, self.change(), self.timestamp);
+        let msg = format!("Transfer recorded: {:?}", transfer);
+
+        account.insert_transfer(self.transaction_id, transfer);
+        log::debug!("{}", msg);
+        Ok(())
+    }
+
+    fn allowed_on_frozen_account(&self) -> bool {
+        self.allowed_on_frozen_account
+    }
+
+    fn reported_as_adjustment(&self) -> bool {
+        true
+    }
+}
+
+/// Applied adjustment, reported separately from other transactions
+#[derive(Debug, serde::Serialize)]
+pub struct AdjustmentEntry {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub timestamp: Option<Timestamp>,
+    /// Change of total funds, negative for debits
+    pub amount: Amount,
+    pub reason: String,
+    pub note: Option<String>,
+}
@@ -0,0 +1,25 @@
+use crate::database::Account;
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::{Amount, TransactionId};
+
+#[derive(Debug, derive_new::new)]
+pub struct Authorize {
+    transaction_id: TransactionId,
+    amount: Amount,
+}
+
+impl Transaction for Authorize {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        if account.contains_authorization(&self.transaction_id)
+            || account.contains_transfer(&self.transaction_id)
+        {
+            Err(TransactionError::reject("Duplicated transaction ID"))?;
+        }
+        if account.headroom() < self.amount {
+            Err(TransactionError::deny("Available funds are not sufficient"))?;
+        }
+        account.amount_held += self.amount;
+        account.insert_authorization(self.transaction_id, self.amount);
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use crate::database::{Account, Transfer, TransferKind};
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::{Amount, Timestamp, TransactionId};
+
+#[derive(Debug, derive_new::new)]
+pub struct Capture {
+    transaction_id: TransactionId,
+    // entire authorized amount when not provided, remaining part is released
+    amount: Option<Amount>,
+    timestamp: Option<Timestamp>,
+}
+
+impl Transaction for Capture {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        let amount_authorized = account.try_get_authorization(&self.transaction_id)?;
+        if account.contains_transfer(&self.transaction_id) {
+            Err(TransactionError::reject("Duplicated transaction ID"))?;
+        }
+        let amount = self.amount.unwrap_or(amount_authorized);
+        if amount > amount_authorized {
+            Err(TransactionError::deny(
+                "Captured amount exceeds authorized amount",
+            ))?;
+        }
+        account.settle_authorization(&self.transaction_id);
+        account.amount_held -= amount_authorized;
+        account.amount_total -= amount;
+
+        let transfer = Transfer::new(TransferKind::Withdrawal, -amount, self.timestamp);
+        let msg = format!("Transfer recorded: {:?}", transfer);
+
+        account.insert_transfer(self.transaction_id, transfer);
+        log::debug!("{}", msg);
+        Ok(())
+    }
+
+    fn withdrawn_amount(&self, account: &Account) -> Option<Amount> {
+        let amount_authorized = account.try_get_authorization(&self.transaction_id).ok();
+        self.amount.or(amount_authorized)
+    }
+}
@@ -1,29 +1,34 @@
 use crate::database::Account;
 use crate::transactions::{Transaction, TransactionError};
-use crate::transport::record::TransactionId;
+use crate::transport::record::{Amount, TransactionId};
 
 #[derive(Debug, derive_new::new)]
 pub struct Chargeback {
     transaction_id: TransactionId,
+    // entire disputed part of the transfer when not provided
+    amount: Option<Amount>,
 }
 
 impl Transaction for Chargeback {
     fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
-        let amount = {
-            let transfer = account.try_get_transfer_mut(&self.transaction_id)?;
+        let closed = account.closed;
+        let transfer = account.try_get_transfer_mut(&self.transaction_id)?;
 
-            if !transfer.disputed {
-                let msg = "Corresponding transfer not disputed";
-                Err(TransactionError::deny(msg))?;
-            }
+        if closed {
+            let amount = transfer.charge_back_late(self.amount)?;
+            account.amount_total -= amount;
+            account.locked = true;
+            return Ok(());
+        }
 
-            transfer.disputed = false;
-            transfer.amount
-        };
+        let amount = transfer.charge_back(self.amount)?;
         account.amount_held -= amount;
         account.amount_total -= amount;
-        account.remove_transfer(&self.transaction_id);
         account.locked = true;
         Ok(())
     }
+
+    fn allowed_on_closed_account(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,37 @@
+use crate::database::{Account, Transfer, TransferKind};
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::{Amount, Timestamp, TransactionId};
+
+#[derive(Debug, derive_new::new)]
+pub struct Close {
+    transaction_id: TransactionId,
+    timestamp: Option<Timestamp>,
+}
+
+impl Transaction for Close {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        if account.contains_transfer(&self.transaction_id) {
+            Err(TransactionError::reject("Duplicated transaction ID"))?;
+        }
+        if account.disputed_transfers().next().is_some() {
+            Err(TransactionError::deny("Account has open disputes"))?;
+        }
+        if !account.amount_held.is_zero() {
+            Err(TransactionError::deny("Account has held funds"))?;
+        }
+        let amount = account.amount_available();
+        if amount < Amount::ZERO {
+            Err(TransactionError::deny("Account has outstanding overdraft"))?;
+        }
+
+        account.amount_total -= amount;
+        account.closed = true;
+
+        let transfer = Transfer::new(TransferKind::Payout, -amount, self.timestamp);
+        let msg = format!("Transfer recorded: {:?}", transfer);
+
+        account.insert_transfer(self.transaction_id, transfer);
+        log::debug!("{}", msg);
+        Ok(())
+    }
+}
@@ -1,11 +1,12 @@
-use crate::database::{Account, Transfer};
+use crate::database::{Account, Transfer, TransferKind};
 use crate::transactions::{Transaction, TransactionError};
-use crate::transport::record::{Amount, TransactionId};
+use crate::transport::record::{Amount, Timestamp, TransactionId};
 
 #[derive(Debug, derive_new::new)]
 pub struct Deposit {
     transaction_id: TransactionId,
     amount: Amount,
+    timestamp: Option<Timestamp>,
 }
 
 impl Transaction for Deposit {
@@ -15,7 +16,7 @@ impl Transaction for Deposit {
         }
         account.amount_total += self.amount;
 
-        let transfer = Transfer::new(self.amount, false);
+        let transfer = Transfer::new(TransferKind::Deposit, self.amount, self.timestamp);
         let msg = format!("Transfer recorded: {:?}", transfer);
 
         account.insert_transfer(self.transaction_id, transfer);
@@ -1,31 +1,31 @@
-use crate::database::Account;
+use crate::database::{Account, Moment};
 use crate::transactions::{Transaction, TransactionError};
-use crate::transport::record::TransactionId;
+use crate::transport::record::{Amount, TransactionId};
+use std::time::Duration;
 
 #[derive(Debug, derive_new::new)]
 pub struct Dispute {
     transaction_id: TransactionId,
+    // entire undisputed part of the transfer when not provided
+    amount: Option<Amount>,
+    now: Moment,
+    window: Option<Duration>,
 }
 
 impl Transaction for Dispute {
     fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
-        let amount = {
-            let amount_available = account.amount_available();
-            let transfer = account.try_get_transfer_mut(&self.transaction_id)?;
+        let amount_available = account.amount_available();
+        let transfer = account.try_get_transfer_mut(&self.transaction_id)?;
 
-            if transfer.disputed {
-                let msg = "Corresponding transfer already disputed";
-                Err(TransactionError::deny(msg))?;
+        if let (Some(now), Some(window), Some(timestamp)) =
+            (self.now.timestamp, self.window, transfer.timestamp)
+        {
+            if now > timestamp.saturating_add(window.as_secs()) {
+                Err(TransactionError::deny("Dispute window expired"))?;
             }
+        }
 
-            if amount_available < transfer.amount {
-                let msg = "Available funds are not sufficient";
-                Err(TransactionError::deny(msg))?;
-            }
-
-            transfer.disputed = true;
-            transfer.amount
-        };
+        let amount = transfer.dispute(self.amount, amount_available, self.now)?;
 
         account.amount_held += amount;
         Ok(())
@@ -1,15 +1,88 @@
-#[derive(Debug)]
+use crate::transport::record::SourcePosition;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum TransactionError {
-    Denied(String),
-    Rejected(String),
+    Denied(Cause),
+    Rejected(Cause),
+    LimitExceeded(Cause),
+}
+
+/// Description of the error, with position of the record that caused it
+#[derive(Debug, Clone, PartialEq, derive_new::new)]
+pub struct Cause {
+    /// Description shared by errors of the same kind, used for grouping them
+    pub reason: String,
+    /// Particulars of the single error, for instance content of the record
+    #[new(default)]
+    pub detail: Option<String>,
+    #[new(default)]
+    pub position: Option<SourcePosition>,
 }
 
 impl TransactionError {
     pub fn deny<T: Into<String>>(cause: T) -> Self {
-        Self::Denied(cause.into())
+        Self::Denied(Cause::new(cause.into()))
     }
 
     pub fn reject<T: Into<String>>(cause: T) -> Self {
-        Self::Rejected(cause.into())
+        Self::Rejected(Cause::new(cause.into()))
+    }
+
+    pub fn exceed<T: Into<String>>(cause: T) -> Self {
+        Self::LimitExceeded(Cause::new(cause.into()))
+    }
+
+    pub fn with_detail<T: Into<String>>(mut self, detail: T) -> Self {
+        self.details_mut().detail = Some(detail.into());
+        self
+    }
+
+    /// Attaches position of the record that caused the error, unless it is already known.
+    pub fn at(mut self, position: Option<SourcePosition>) -> Self {
+        let details = self.details_mut();
+        if details.position.is_none() {
+            details.position = position;
+        }
+        self
+    }
+
+    /// Prepends the context to the reason, keeping kind, detail and position of the error.
+    pub fn context(mut self, context: &str) -> Self {
+        let details = self.details_mut();
+        details.reason = format!("{}: {}", context, details.reason);
+        self
+    }
+
+    /// Reason followed by the detail, when there is one
+    pub fn cause(&self) -> String {
+        let details = self.details();
+        match &details.detail {
+            Some(detail) => format!("{}: {}", details.reason, detail),
+            None => details.reason.clone(),
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.details().reason
+    }
+
+    pub fn detail(&self) -> Option<&str> {
+        self.details().detail.as_deref()
+    }
+
+    pub fn position(&self) -> Option<&SourcePosition> {
+        self.details().position.as_ref()
+    }
+
+    fn details(&self) -> &Cause {
+        match self {
+            Self::Denied(cause) | Self::Rejected(cause) | Self::LimitExceeded(cause) => cause,
+        }
+    }
+
+    fn details_mut(&mut self) -> &mut Cause {
+        match self {
+            Self::Denied(cause) | Self::Rejected(cause) | Self::LimitExceeded(cause) => cause,
+        }
     }
 }
@@ -0,0 +1,31 @@
+use crate::database::{Account, Transfer, TransferKind};
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::{Amount, Timestamp, TransactionId};
+
+/// Credits interest accrued by the account. Not produced by records of the input file.
+#[derive(Debug, derive_new::new)]
+pub struct Interest {
+    transaction_id: TransactionId,
+    amount: Amount,
+    timestamp: Option<Timestamp>,
+}
+
+impl Transaction for Interest {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        if account.contains_transfer(&self.transaction_id) {
+            Err(TransactionError::reject("Duplicated transaction ID"))?;
+        }
+        account.amount_total += self.amount;
+
+        let transfer = Transfer::new(TransferKind::Interest, self.amount, self.timestamp);
+        let msg = format!("Transfer recorded: {:?}", transfer);
+
+        account.insert_transfer(self.transaction_id, transfer);
+        log::debug!("{}", msg);
+        Ok(())
+    }
+
+    fn allowed_on_frozen_account(&self) -> bool {
+        true
+    }
+}
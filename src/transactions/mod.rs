@@ -1,15 +1,33 @@
+mod adjustment;
+mod authorize;
+mod capture;
 mod chargeback;
+mod close;
 mod deposit;
 mod dispute;
 mod errors;
+mod interest;
+mod open;
 mod resolve;
+mod reversal;
+mod set_limit;
 mod transaction;
+mod void;
 mod withdrawal;
 
+pub use crate::transactions::adjustment::{Adjustment, AdjustmentEntry, AdjustmentKind};
+pub use crate::transactions::authorize::Authorize;
+pub use crate::transactions::capture::Capture;
 pub use crate::transactions::chargeback::Chargeback;
+pub use crate::transactions::close::Close;
 pub use crate::transactions::deposit::Deposit;
 pub use crate::transactions::dispute::Dispute;
 pub use crate::transactions::errors::TransactionError;
+pub use crate::transactions::interest::Interest;
+pub use crate::transactions::open::Open;
 pub use crate::transactions::resolve::Resolve;
+pub use crate::transactions::reversal::Reversal;
+pub use crate::transactions::set_limit::SetLimit;
 pub use crate::transactions::transaction::Transaction;
+pub use crate::transactions::void::Void;
 pub use crate::transactions::withdrawal::Withdrawal;
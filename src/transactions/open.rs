@@ -0,0 +1,23 @@
+use crate::database::Account;
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::Amount;
+
+#[derive(Debug, derive_new::new)]
+pub struct Open {
+    tier: Option<String>,
+    currency: Option<String>,
+    credit_limit: Option<Amount>,
+}
+
+impl Transaction for Open {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        account.tier = self.tier.clone();
+        account.currency = self.currency.clone();
+        account.credit_limit = self.credit_limit.unwrap_or_default();
+        Ok(())
+    }
+
+    fn opens_account(&self) -> bool {
+        true
+    }
+}
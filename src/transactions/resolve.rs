@@ -1,25 +1,19 @@
 use crate::database::Account;
 use crate::transactions::{Transaction, TransactionError};
-use crate::transport::record::TransactionId;
+use crate::transport::record::{Amount, TransactionId};
 
 #[derive(Debug, derive_new::new)]
 pub struct Resolve {
     transaction_id: TransactionId,
+    // entire disputed part of the transfer when not provided
+    amount: Option<Amount>,
 }
 
 impl Transaction for Resolve {
     fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
-        let amount = {
-            let transfer = account.try_get_transfer_mut(&self.transaction_id)?;
+        let transfer = account.try_get_transfer_mut(&self.transaction_id)?;
+        let amount = transfer.resolve(self.amount)?;
 
-            if !transfer.disputed {
-                let msg = "Corresponding transfer not disputed";
-                Err(TransactionError::deny(msg))?;
-            }
-
-            transfer.disputed = false;
-            transfer.amount
-        };
         account.amount_held -= amount;
         Ok(())
     }
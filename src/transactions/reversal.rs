@@ -0,0 +1,25 @@
+use crate::database::Account;
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::TransactionId;
+
+#[derive(Debug, derive_new::new)]
+pub struct Reversal {
+    transaction_id: TransactionId,
+    /// Funds of the account may go negative when reversing a deposit
+    overdraft_allowed: bool,
+}
+
+impl Transaction for Reversal {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        let amount_available = Some(account.amount_available()).filter(|_| !self.overdraft_allowed);
+        let transfer = account.try_get_transfer_mut(&self.transaction_id)?;
+        let amount = transfer.reverse(amount_available)?;
+
+        account.amount_total -= amount;
+        Ok(())
+    }
+
+    fn allowed_on_frozen_account(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,18 @@
+use crate::database::Account;
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::Amount;
+
+#[derive(Debug, derive_new::new)]
+pub struct SetLimit {
+    credit_limit: Amount,
+}
+
+impl Transaction for SetLimit {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        if account.amount_available() + self.credit_limit < Amount::ZERO {
+            Err(TransactionError::deny("Credit limit below used overdraft"))?;
+        }
+        account.credit_limit = self.credit_limit;
+        Ok(())
+    }
+}
@@ -1,14 +1,34 @@
 use crate::database::Account;
 use crate::transactions::TransactionError;
+use crate::transport::record::Amount;
 
-pub trait Transaction {
+pub trait Transaction: std::fmt::Debug {
     fn execute(&self, account: &mut Account) -> Result<(), TransactionError>;
 
     fn allowes_account_creation(&self) -> bool {
         false
     }
 
+    /// Account must not exist yet and is created regardless of `Config::require_open`
+    fn opens_account(&self) -> bool {
+        false
+    }
+
     fn allowed_on_frozen_account(&self) -> bool {
         false
     }
+
+    fn allowed_on_closed_account(&self) -> bool {
+        false
+    }
+
+    /// Listed in the report of manual adjustments
+    fn reported_as_adjustment(&self) -> bool {
+        false
+    }
+
+    /// Amount subject to velocity limits, given the account before the transaction
+    fn withdrawn_amount(&self, _account: &Account) -> Option<Amount> {
+        None
+    }
 }
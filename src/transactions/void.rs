@@ -0,0 +1,21 @@
+use crate::database::Account;
+use crate::transactions::{Transaction, TransactionError};
+use crate::transport::record::TransactionId;
+
+#[derive(Debug, derive_new::new)]
+pub struct Void {
+    transaction_id: TransactionId,
+}
+
+impl Transaction for Void {
+    fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
+        let amount_authorized = account.try_get_authorization(&self.transaction_id)?;
+        account.settle_authorization(&self.transaction_id);
+        account.amount_held -= amount_authorized;
+        Ok(())
+    }
+
+    fn allowed_on_frozen_account(&self) -> bool {
+        true
+    }
+}
@@ -1,20 +1,33 @@
-use crate::database::Account;
+use crate::database::{Account, Transfer, TransferKind};
 use crate::transactions::{Transaction, TransactionError};
-use crate::transport::record::{Amount, TransactionId};
+use crate::transport::record::{Amount, Timestamp, TransactionId};
 
 #[derive(Debug, derive_new::new)]
 pub struct Withdrawal {
-    // used only for logging and for future purposes
-    _transaction_id: TransactionId,
+    transaction_id: TransactionId,
     amount: Amount,
+    timestamp: Option<Timestamp>,
 }
 
 impl Transaction for Withdrawal {
     fn execute(&self, account: &mut Account) -> Result<(), TransactionError> {
-        if account.amount_available() < self.amount {
+        if account.contains_transfer(&self.transaction_id) {
+            Err(TransactionError::reject("Duplicated transaction ID"))?;
+        }
+        if account.headroom() < self.amount {
             Err(TransactionError::deny("Available funds are not sufficient"))?;
         }
         account.amount_total -= self.amount;
+
+        let transfer = Transfer::new(TransferKind::Withdrawal, -self.amount, self.timestamp);
+        let msg = format!("Transfer recorded: {:?}", transfer);
+
+        account.insert_transfer(self.transaction_id, transfer);
+        log::debug!("{}", msg);
         Ok(())
     }
+
+    fn withdrawn_amount(&self, _account: &Account) -> Option<Amount> {
+        Some(self.amount)
+    }
 }
@@ -0,0 +1,19 @@
+use crate::transport::record::{Amount, ClientId};
+use std::path::Path;
+
+/// Row of an accounts file, as produced by `CsvExporter::dump_accounts`.
+#[derive(Debug, serde::Deserialize)]
+pub struct ExpectedAccount {
+    pub client: ClientId,
+    pub available: Amount,
+    pub held: Amount,
+    pub total: Amount,
+    pub locked: bool,
+}
+
+pub fn read_expected_accounts(path: &Path) -> Result<Vec<ExpectedAccount>, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+    reader.deserialize::<ExpectedAccount>().collect()
+}
@@ -1,29 +1,84 @@
 use crate::database::Account;
-use crate::transport::record::ClientId;
-use std::collections::HashMap;
+use crate::transport::record::{ClientId, AMOUNT_SCALE};
 
 #[derive(derive_new::new)]
 pub struct CsvExporter<W: std::io::Write> {
     writer: csv::Writer<W>,
+    /// Adds `closed` column to accounts
+    #[new(default)]
+    closed: bool,
+    /// Adds `limit` and `headroom` columns to accounts
+    #[new(default)]
+    limits: bool,
 }
 
 impl<W: std::io::Write> CsvExporter<W> {
-    pub fn dump_accounts(
+    pub fn with_closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: bool) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn dump_accounts<'a>(
         &mut self,
-        accounts: &HashMap<ClientId, Account>,
+        accounts: impl IntoIterator<Item = (&'a ClientId, &'a Account)>,
     ) -> Result<(), csv::Error> {
-        let header = &["client", "available", "held", "total", "locked"];
-        self.writer.write_record(header)?;
-        for (client_id, account) in accounts.iter() {
-            self.writer.serialize((
-                client_id,
-                account.amount_available(),
-                account.amount_held,
-                account.amount_total,
-                account.locked,
-            ))?;
+        let mut header = vec!["client", "available", "held", "total", "locked"];
+        if self.closed {
+            header.push("closed");
+        }
+        if self.limits {
+            header.extend(["limit", "headroom"]);
+        }
+        self.writer.write_record(&header)?;
+        for (client_id, account) in accounts {
+            let mut row = vec![
+                client_id.to_string(),
+                account.amount_available().to_string(),
+                account.amount_held.to_string(),
+                account.amount_total.to_string(),
+                account.locked.to_string(),
+            ];
+            if self.closed {
+                row.push(account.closed.to_string());
+            }
+            if self.limits {
+                let mut limit = account.credit_limit;
+                limit.rescale(AMOUNT_SCALE);
+                row.push(limit.to_string());
+                row.push(account.headroom().to_string());
+            }
+            self.writer.write_record(&row)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn dump<T: serde::Serialize>(&mut self, rows: &[T]) -> Result<(), csv::Error> {
+        for row in rows {
+            self.writer.serialize(row)?;
         }
         self.writer.flush()?;
         Ok(())
     }
 }
+
+#[derive(derive_new::new)]
+pub struct JsonExporter<W: std::io::Write> {
+    writer: W,
+}
+
+impl<W: std::io::Write> JsonExporter<W> {
+    pub fn dump<T: serde::Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        serde_json::to_writer_pretty(&mut self.writer, value)?;
+        writeln!(self.writer).map_err(serde_json::Error::io)?;
+        self.writer.flush().map_err(serde_json::Error::io)
+    }
+}
@@ -1,20 +1,103 @@
-use crate::transport::record::Record;
-use std::path::PathBuf;
+use crate::transport::record::{Record, SourcePosition};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
 
-pub struct CvsFileImporter {
-    reader: csv::Reader<std::fs::File>,
+pub struct CsvImporter<R: Read> {
+    reader: csv::Reader<R>,
+    /// Name of the input, given in positions of the records
+    file: Option<Arc<str>>,
 }
 
-impl CvsFileImporter {
-    pub fn new(transactions: PathBuf) -> Result<Self, csv::Error> {
+impl CsvImporter<File> {
+    pub fn from_path(transactions: &Path) -> Result<Self, csv::Error> {
+        let mut importer = Self::new(File::open(transactions)?);
+        importer.file = Some(transactions.display().to_string().into());
+        Ok(importer)
+    }
+}
+
+impl<R: Read> CsvImporter<R> {
+    pub fn new(reader: R) -> Self {
         let reader = csv::ReaderBuilder::new()
-            .quoting(false)
             .trim(csv::Trim::All)
-            .from_path(transactions)?;
-        Ok(Self { reader })
+            .from_reader(reader);
+        Self { reader, file: None }
+    }
+
+    pub fn into_rows(self) -> Rows<R> {
+        Rows {
+            reader: self.reader,
+            file: self.file,
+            headers: None,
+            failed: false,
+            row: csv::StringRecord::new(),
+        }
+    }
+}
+
+/// Row of the input that could not be read, with the position it was read from
+#[derive(Debug)]
+pub struct ImportError {
+    pub error: csv::Error,
+    pub position: Option<SourcePosition>,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
     }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Records of the input, each carrying the position it was read from
+pub struct Rows<R: Read> {
+    reader: csv::Reader<R>,
+    file: Option<Arc<str>>,
+    headers: Option<csv::StringRecord>,
+    /// Headers could not be read, so there are no records to read either
+    failed: bool,
+    row: csv::StringRecord,
+}
+
+impl<R: Read> Iterator for Rows<R> {
+    type Item = Result<Record, ImportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if self.headers.is_none() {
+            match self.reader.headers() {
+                Ok(headers) => self.headers = Some(headers.clone()),
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(self.error(err)));
+                }
+            }
+        }
+        match self.reader.read_record(&mut self.row) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(err) => return Some(Err(self.error(err))),
+        }
+        let record = self.row.deserialize::<Record>(self.headers.as_ref());
+        let record = record.map_err(|err| self.error(err));
+        Some(record.map(|mut record| {
+            let position = self.row.position();
+            record.position = position.map(|pos| SourcePosition::new(self.file.clone(), pos));
+            record
+        }))
+    }
+}
 
-    pub fn read_rows(&mut self) -> csv::DeserializeRecordsIter<'_, std::fs::File, Record> {
-        self.reader.deserialize::<Record>()
+impl<R: Read> Rows<R> {
+    fn error(&self, error: csv::Error) -> ImportError {
+        let position = error.position();
+        let position = position.map(|pos| SourcePosition::new(self.file.clone(), pos));
+        ImportError { error, position }
     }
 }
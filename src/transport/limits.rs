@@ -0,0 +1,29 @@
+use crate::config::VelocityLimits;
+use crate::transport::record::{Amount, ClientId};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, serde::Deserialize)]
+struct ClientLimits {
+    client: ClientId,
+    max_amount: Option<Amount>,
+    max_total: Option<Amount>,
+    max_count: Option<usize>,
+}
+
+pub fn read_client_limits(path: &Path) -> Result<HashMap<ClientId, VelocityLimits>, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+    let mut limits = HashMap::new();
+    for row in reader.deserialize::<ClientLimits>() {
+        let row = row?;
+        let client_limits = VelocityLimits {
+            max_amount: row.max_amount,
+            max_total: row.max_total,
+            max_count: row.max_count,
+        };
+        limits.insert(row.client, client_limits);
+    }
+    Ok(limits)
+}
@@ -0,0 +1,58 @@
+use crate::metrics::SharedMetrics;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+/// Requests are served one by one, so an idle client must not hold the others for long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves metrics in Prometheus text format under `/metrics`, from a background thread.
+pub fn expose_metrics(address: &str, metrics: SharedMetrics) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    log::info!(
+        "Metrics exposed on http://{}/metrics",
+        listener.local_addr()?
+    );
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let outcome = stream.and_then(|stream| respond(stream, &metrics));
+            if let Err(err) = outcome {
+                log::warn!("Metrics request failed: {}", err);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(stream: TcpStream, metrics: &SharedMetrics) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = if path == "/metrics" {
+        let body = match metrics.lock() {
+            Ok(metrics) => metrics.to_string(),
+            Err(poisoned) => poisoned.into_inner().to_string(),
+        };
+        ("200 OK", body)
+    } else {
+        ("404 Not Found", String::new())
+    };
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
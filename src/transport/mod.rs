@@ -1,6 +1,12 @@
+mod expected;
 mod exporter;
 mod importer;
+mod limits;
+mod metrics_endpoint;
 pub mod record;
 
-pub use crate::transport::exporter::CsvExporter;
-pub use crate::transport::importer::CvsFileImporter;
+pub use crate::transport::expected::{read_expected_accounts, ExpectedAccount};
+pub use crate::transport::exporter::{CsvExporter, JsonExporter};
+pub use crate::transport::importer::{CsvImporter, ImportError};
+pub use crate::transport::limits::read_client_limits;
+pub use crate::transport::metrics_endpoint::expose_metrics;
@@ -1,34 +1,135 @@
 use crate::transactions::TransactionError;
 use rust_decimal::Decimal;
+use std::fmt;
+use std::sync::Arc;
 
 pub type ClientId = u16;
 pub type TransactionId = u32;
 pub type Amount = Decimal;
-const AMOUNT_SCALE: u32 = 4;
+/// Seconds since the Unix epoch
+pub type Timestamp = u64;
+pub const AMOUNT_SCALE: u32 = 4;
 
-#[derive(Debug, serde::Deserialize, derive_new::new)]
+#[derive(Debug, Clone, serde::Deserialize, derive_new::new)]
 pub struct Record {
     pub r#type: String,
     pub client: ClientId,
     pub tx: TransactionId,
     amount: Option<Decimal>,
+    #[serde(default)]
+    #[new(default)]
+    pub timestamp: Option<Timestamp>,
+    #[serde(default)]
+    #[new(default)]
+    pub tier: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    pub currency: Option<String>,
+    /// Reason code of manual adjustments
+    #[serde(default)]
+    #[new(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    pub note: Option<String>,
+    /// Place in the input the record was read from
+    #[serde(skip)]
+    #[new(default)]
+    pub position: Option<SourcePosition>,
+}
+
+/// Place in the input where a record starts
+#[derive(Clone, PartialEq)]
+pub struct SourcePosition {
+    /// Name of the input file, unknown for the standard input
+    pub file: Option<Arc<str>>,
+    pub line: u64,
+    /// Offset from the beginning of the input
+    pub byte: u64,
+}
+
+impl SourcePosition {
+    pub fn new(file: Option<Arc<str>>, position: &csv::Position) -> Self {
+        Self {
+            file,
+            line: position.line(),
+            byte: position.byte(),
+        }
+    }
+}
+
+impl fmt::Display for SourcePosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{} (byte {})", file, self.line, self.byte),
+            None => write!(f, "line {} (byte {})", self.line, self.byte),
+        }
+    }
+}
+
+impl fmt::Debug for SourcePosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl serde::Serialize for SourcePosition {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
 }
 
 impl Record {
+    /// Amount as given in the input, before any validation
+    pub fn given_amount(&self) -> Option<Decimal> {
+        self.amount
+    }
+
     pub fn amount(&self) -> Result<Amount, TransactionError> {
         match self.amount {
             Some(amount) => {
                 if amount <= Decimal::new(0, 0) {
-                    let msg = format!("Amount must be positive in: {:?}", self);
-                    Err(TransactionError::reject(msg))
+                    let detail = format!("{:?}", self);
+                    Err(TransactionError::reject("Amount must be positive").with_detail(detail))
                 } else {
                     Ok(amount.trunc_with_scale(AMOUNT_SCALE))
                 }
             }
             None => {
-                let msg = format!("Amount missing in: {:?}", self);
-                Err(TransactionError::reject(msg))
+                let detail = format!("{:?}", self);
+                Err(TransactionError::reject("Amount missing").with_detail(detail))
+            }
+        }
+    }
+
+    pub fn reason_code(&self) -> Result<&str, TransactionError> {
+        match self.reason.as_deref() {
+            Some(reason) if !reason.is_empty() => Ok(reason),
+            _ => {
+                let detail = format!("{:?}", self);
+                Err(TransactionError::reject("Reason code missing").with_detail(detail))
             }
         }
     }
+
+    pub fn non_negative_amount(&self) -> Result<Amount, TransactionError> {
+        match self.amount {
+            Some(amount) if amount.is_zero() => Ok(Amount::ZERO),
+            _ => self.amount(),
+        }
+    }
+
+    pub fn optional_amount(&self) -> Result<Option<Amount>, TransactionError> {
+        match self.amount {
+            Some(_) => self.amount().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn optional_non_negative_amount(&self) -> Result<Option<Amount>, TransactionError> {
+        match self.amount {
+            Some(_) => self.non_negative_amount().map(Some),
+            None => Ok(None),
+        }
+    }
 }